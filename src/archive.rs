@@ -0,0 +1,371 @@
+use crate::files::{SkipReason, Skipped};
+use anyhow::{anyhow, bail, Result};
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use tempfile::TempDir;
+
+/// Bytes unpacked from one archive at most, so a zip bomb cannot fill the
+/// temporary directory.
+const MAX_EXTRACTED: u64 = 1 << 30;
+
+/// Archive formats accepted as the input path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// The archive format of `path`, judged by its name.
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `path` is a zip or tar archive that [`Archive::extract`] can
+/// read.
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && ArchiveKind::of(path).is_some()
+}
+
+/// The entries of an archive (or of a git revision, see
+/// [`crate::revision`]), unpacked into a private temporary directory that is
+/// removed when this is dropped.
+///
+/// The walker, ignore files and every reader work on paths, so unpacking
+/// lets the archive be collected exactly like a directory: excludes,
+/// language detection and `.gitignore` files inside the archive all apply.
+#[derive(Debug)]
+pub struct Archive {
+    _dir: TempDir,
+    root: PathBuf,
+    /// Entries that were not unpacked because they are too large.
+    skipped: Vec<Skipped>,
+}
+
+impl Archive {
+    /// Unpack the regular files and directories of the archive at `path`.
+    ///
+    /// Symbolic links, entries escaping the archive root, anything under
+    /// `.git` and files larger than `max_size` bytes are left out; the latter
+    /// are listed by [`Archive::skipped`]. Fails once more than
+    /// [`MAX_EXTRACTED`] bytes would be unpacked.
+    pub fn extract(path: &Path, max_size: Option<u64>) -> Result<Self> {
+        let kind = ArchiveKind::of(path)
+            .ok_or_else(|| anyhow!("{} is not a zip or tar archive", path.display()))?;
+        let mut archive = Archive::empty(&archive_stem(path))?;
+
+        let file = File::open(path)?;
+        let mut limits = Limits {
+            max_size: max_size.unwrap_or(u64::MAX),
+            remaining: MAX_EXTRACTED,
+            skipped: Vec::new(),
+        };
+        match kind {
+            ArchiveKind::Zip => unpack_zip(file, archive.root(), &mut limits)?,
+            ArchiveKind::Tar => unpack_tar(file, archive.root(), &mut limits)?,
+            ArchiveKind::TarGz => unpack_tar(GzDecoder::new(file), archive.root(), &mut limits)?,
+        }
+        archive.skipped = limits.skipped;
+        Ok(archive)
+    }
+
+    /// An empty root directory called `name`, marked as a repository root
+    /// so that ignore files inside it apply and those above it do not.
+    pub fn empty(name: &str) -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("copycat-").tempdir()?;
+        let root = dir.path().join(name);
+        fs::create_dir_all(root.join(".git"))?;
+        Ok(Archive {
+            _dir: dir,
+            root,
+            skipped: Vec::new(),
+        })
+    }
+
+    /// The same unpacked files, collected from `rel` below the current root
+    /// instead; ignore files above it still apply.
+    pub fn with_root(self, rel: &Path) -> Self {
+        Archive {
+            root: self.root.join(rel),
+            ..self
+        }
+    }
+
+    /// The directory holding the archive's entries, named after the archive.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The entries left out for their size, at the paths they would have
+    /// been unpacked to.
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+}
+
+/// The archive's file name without its archive extensions, e.g. `repro` for
+/// `repro.tar.gz`.
+fn archive_stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map_or_else(|| "archive".into(), |n| n.to_string_lossy());
+    let lower = name.to_lowercase();
+    let stem_len = [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name.len(), |ext| name.len() - ext.len());
+    match &name[..stem_len] {
+        "" => "archive".to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+/// Whether an entry at the relative path `rel` may be unpacked: it must stay
+/// inside the root and not be part of a `.git` directory.
+pub fn is_safe_entry(rel: &Path) -> bool {
+    rel.components().all(|c| match c {
+        Component::Normal(name) => name != ".git",
+        Component::CurDir => true,
+        _ => false,
+    })
+}
+
+/// Size limits applied while unpacking.
+struct Limits {
+    /// Largest file unpacked; larger entries are skipped.
+    max_size: u64,
+    /// Bytes left of [`MAX_EXTRACTED`].
+    remaining: u64,
+    /// Entries skipped for being larger than `max_size`.
+    skipped: Vec<Skipped>,
+}
+
+impl Limits {
+    /// Write the entry in `reader`, which claims to be `size` bytes, to
+    /// `target` unless it is too large, in which case it is recorded as
+    /// skipped. Entries are never trusted to be as small as their header
+    /// says.
+    fn unpack(&mut self, reader: &mut impl Read, size: u64, target: &Path) -> Result<()> {
+        if size > self.max_size {
+            let detail = format!("{} bytes, limit is {}", size, self.max_size);
+            self.skipped.push(Skipped::new(
+                target.to_path_buf(),
+                SkipReason::TooLarge,
+                Some(detail),
+            ));
+            return Ok(());
+        }
+        if size > self.remaining {
+            bail!(
+                "unpacks to more than {}",
+                crate::stats::format_bytes(MAX_EXTRACTED)
+            );
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let limit = size.min(self.remaining);
+        let written = io::copy(&mut reader.take(limit), &mut File::create(target)?)?;
+        self.remaining -= written;
+        Ok(())
+    }
+}
+
+fn unpack_zip(file: File, root: &Path, limits: &mut Limits) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(file)?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_symlink() {
+            continue;
+        }
+        let Some(rel) = entry.enclosed_name().filter(|rel| is_safe_entry(rel)) else {
+            continue;
+        };
+        let target = root.join(rel);
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            let size = entry.size();
+            limits.unpack(&mut entry, size, &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn unpack_tar(reader: impl Read, root: &Path, limits: &mut Limits) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        let rel = entry.path()?.into_owned();
+        if !is_safe_entry(&rel) {
+            continue;
+        }
+        let target = root.join(rel);
+        if kind.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else if kind.is_file() {
+            let size = entry.size();
+            limits.unpack(&mut entry, size, &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{collect_files, WalkOptions};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    const ENTRIES: &[(&str, &str)] = &[
+        ("repro/.gitignore", "*.log\n"),
+        ("repro/src/main.rs", "fn main() {}\n"),
+        ("repro/debug.log", "noise\n"),
+        ("repro/README.md", "# Repro\n"),
+    ];
+
+    fn collected(archive: &Archive) -> Vec<String> {
+        let files = collect_files(
+            archive.root().to_path_buf(),
+            &[],
+            true,
+            &WalkOptions::default(),
+        )
+        .unwrap();
+        files
+            .iter()
+            .map(|f| {
+                f.strip_prefix(archive.root())
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("repro.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in ENTRIES {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.start_file("../escape.rs", options).unwrap();
+        zip.write_all(b"fn escape() {}\n").unwrap();
+        zip.finish().unwrap();
+
+        assert!(is_archive(&path));
+        let archive = Archive::extract(&path, None).unwrap();
+        assert!(archive.root().ends_with("repro"));
+        assert_eq!(
+            collected(&archive),
+            vec!["repro/.gitignore", "repro/README.md", "repro/src/main.rs"]
+        );
+        assert!(!archive.root().parent().unwrap().join("escape.rs").exists());
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("Repro.TGZ");
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, contents) in ENTRIES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        tar.append_link(&mut header, "repro/passwd", "/etc/passwd")
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let archive = Archive::extract(&path, None).unwrap();
+        assert!(archive.root().ends_with("Repro"));
+        assert_eq!(
+            collected(&archive),
+            vec!["repro/.gitignore", "repro/README.md", "repro/src/main.rs"]
+        );
+
+        let root = archive.root().to_path_buf();
+        drop(archive);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_extract_skips_large_entries() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("repro.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in ENTRIES {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.start_file("repro/data.bin", options).unwrap();
+        zip.write_all(&[b'x'; 1000]).unwrap();
+        zip.finish().unwrap();
+
+        let archive = Archive::extract(&path, Some(100)).unwrap();
+        assert_eq!(
+            collected(&archive),
+            vec!["repro/.gitignore", "repro/README.md", "repro/src/main.rs"]
+        );
+        assert_eq!(
+            archive.skipped(),
+            [Skipped::new(
+                archive.root().join("repro/data.bin"),
+                SkipReason::TooLarge,
+                Some("1000 bytes, limit is 100".to_string())
+            )]
+        );
+
+        let mut limits = Limits {
+            max_size: u64::MAX,
+            remaining: 10,
+            skipped: Vec::new(),
+        };
+        let target = dir.path().join("big");
+        assert!(limits.unpack(&mut &[0u8; 20][..], 20, &target).is_err());
+        limits.unpack(&mut &[0u8; 20][..], 5, &target).unwrap();
+        assert_eq!(fs::read(&target).unwrap().len(), 5);
+        assert_eq!(limits.remaining, 5);
+    }
+
+    #[test]
+    fn test_not_an_archive() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "text").unwrap();
+        assert!(!is_archive(&path));
+        assert!(!is_archive(dir.path()));
+        assert_eq!(archive_stem(Path::new("a/b.tar.gz")), "b");
+        assert_eq!(archive_stem(Path::new(".zip")), "archive");
+    }
+}
@@ -1,13 +1,140 @@
 use anyhow::Result;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
 use ignore::overrides::{Override, OverrideBuilder};
-use ignore::WalkBuilder;
+use ignore::{Match, WalkBuilder};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Why a path was left out of the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Matched a `.gitignore` (or other ignore file) rule.
+    Ignored,
+    /// Matched a user-supplied `--exclude` pattern.
+    Excluded,
+    /// Contents are not valid UTF-8 text.
+    Binary,
+    /// Larger than the configured size limit.
+    TooLarge,
+    /// Could not be read from disk.
+    Unreadable,
+    /// Detected as a language left out by `--lang` or `--exclude-lang`.
+    OtherLanguage,
+    /// Not pulled in by the `--follow` entry file.
+    NotImported,
+    /// Left out by the `--grep` patterns.
+    GrepFiltered,
+    /// Not among the files kept by `--rank`.
+    NotRelevant,
+    /// Unchanged since the last copy, with `--since-last`.
+    Unchanged,
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SkipReason::Ignored => "ignored",
+            SkipReason::Excluded => "excluded",
+            SkipReason::Binary => "binary",
+            SkipReason::TooLarge => "too large",
+            SkipReason::Unreadable => "unreadable",
+            SkipReason::OtherLanguage => "other language",
+            SkipReason::NotImported => "not imported",
+            SkipReason::GrepFiltered => "grep filtered",
+            SkipReason::NotRelevant => "not relevant",
+            SkipReason::Unchanged => "unchanged",
+        };
+        f.pad(name)
+    }
+}
+
+/// A path that was left out of the output.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+    /// What caused the skip, e.g. the ignore rule and the file it came from.
+    pub detail: Option<String>,
+}
+
+impl Skipped {
+    pub fn new(path: PathBuf, reason: SkipReason, detail: Option<String>) -> Self {
+        Skipped {
+            path,
+            reason,
+            detail,
+        }
+    }
+}
+
+/// Name of the copycat-specific ignore file. It uses gitignore syntax, is
+/// honored at any directory level (inside a git repository or not) and
+/// takes precedence over `.ignore` and `.gitignore`.
+pub const COPYCAT_IGNORE: &str = ".copycatignore";
+
+/// Options controlling how the directory walker traverses the tree and
+/// which ignore sources it honors.
+///
+/// The defaults follow symlinks, include hidden files and honor `.gitignore`,
+/// `.ignore` and the global gitignore, but not `.git/info/exclude`.
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Follow symbolic links to files and directories.
+    pub follow_links: bool,
+    /// Skip hidden files and directories (names starting with `.`).
+    pub skip_hidden: bool,
+    /// Honor `.gitignore` files.
+    pub git_ignore: bool,
+    /// Honor the repository's `.git/info/exclude`.
+    pub git_exclude: bool,
+    /// Honor the global gitignore (`core.excludesFile`).
+    pub git_global: bool,
+    /// Maximum directory depth to descend into; the root's children are depth 1.
+    pub max_depth: Option<usize>,
+    /// Do not cross file system boundaries.
+    pub same_file_system: bool,
+    /// Additional ignore file names using gitignore syntax, e.g. `.llmignore`.
+    /// These take precedence over [`COPYCAT_IGNORE`], `.ignore` and `.gitignore`,
+    /// later names over earlier ones.
+    pub ignore_filenames: Vec<String>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            follow_links: true,
+            skip_hidden: false,
+            git_ignore: true,
+            git_exclude: false,
+            git_global: true,
+            max_depth: None,
+            same_file_system: false,
+            ignore_filenames: Vec::new(),
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Whether `name` is one of copycat's own ignore files:
+    /// [`COPYCAT_IGNORE`] or one of [`WalkOptions::ignore_filenames`].
+    fn is_ignore_file(&self, name: &std::ffi::OsStr) -> bool {
+        name == COPYCAT_IGNORE || self.ignore_filenames.iter().any(|n| name == n.as_str())
+    }
+}
+
 /// Collect files from `path`, honoring .gitignore and
 /// additional user-defined exclude patterns via `excludes`.
 ///
 /// We'll prefix each exclude pattern with `!`, which means “exclude” in override logic.
-pub fn collect_files(path: PathBuf, excludes: &[String], sort: bool) -> Result<Vec<PathBuf>> {
+pub fn collect_files(
+    path: PathBuf,
+    excludes: &[String],
+    sort: bool,
+    options: &WalkOptions,
+) -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     if path.is_file() {
@@ -16,21 +143,10 @@ pub fn collect_files(path: PathBuf, excludes: &[String], sort: bool) -> Result<V
     }
 
     // Build override rules with forced exclusion
-    let overrides = build_override(excludes, &path)?;
+    let overrides = build_override(excludes, &path, options)?;
 
-    let walker = WalkBuilder::new(&path)
-        .standard_filters(true)
-        .hidden(false)
-        .follow_links(true)
-        .ignore(true)
-        .git_ignore(true)
-        .git_exclude(false)
-        .git_global(true)
-        .overrides(overrides)
-        .build();
-
-    for result in walker {
-        let entry = result?;
+    for result in build_walker(&path, overrides, options) {
+        let entry = result.map_err(walk_error)?;
         if matches!(entry.file_type(), Some(ft) if ft.is_file()) {
             files.push(entry.path().to_path_buf());
         }
@@ -43,11 +159,288 @@ pub fn collect_files(path: PathBuf, excludes: &[String], sort: bool) -> Result<V
     Ok(files)
 }
 
+/// Collect the directories below `path` that the walker descends into,
+/// honoring the same ignore rules and excludes as [`collect_files`].
+/// Unlike the files, this includes empty directories; the root itself is
+/// not part of the result.
+pub fn collect_dirs(
+    path: &Path,
+    excludes: &[String],
+    options: &WalkOptions,
+) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+
+    if path.is_file() {
+        return Ok(dirs);
+    }
+
+    let overrides = build_override(excludes, path, options)?;
+    for result in build_walker(path, overrides, options) {
+        let entry = result.map_err(walk_error)?;
+        if entry.depth() > 0 && matches!(entry.file_type(), Some(ft) if ft.is_dir()) {
+            dirs.push(entry.into_path());
+        }
+    }
+
+    Ok(dirs)
+}
+
+/// Lists the paths under `path` that [`collect_files`] left out of `included`,
+/// together with the reason they were skipped and the rule responsible.
+///
+/// The directory is walked a second time with the ignore files applied but
+/// without the user's exclude patterns: anything that walk sees is excluded,
+/// anything it does not see is ignored. Directories skipped as a whole are
+/// reported once rather than file by file.
+pub fn collect_skipped(
+    path: &Path,
+    excludes: &[String],
+    included: &[PathBuf],
+    options: &WalkOptions,
+) -> Result<Vec<Skipped>> {
+    let mut skipped = Vec::new();
+
+    if path.is_file() {
+        return Ok(skipped);
+    }
+
+    let included: HashSet<&Path> = included.iter().map(|p| p.as_path()).collect();
+    let user_excludes = build_exclude_matcher(excludes, path)?;
+    let global = if options.git_global {
+        Gitignore::global().0
+    } else {
+        Gitignore::empty()
+    };
+
+    let mut visible = Vec::new();
+    for result in build_walker(path, build_override(&[], path, options)?, options) {
+        let entry = result.map_err(walk_error)?;
+        let depth = entry.depth();
+        let file_type = entry.file_type();
+        visible.push((entry.into_path(), depth, file_type));
+    }
+    let visible_set: HashSet<&Path> = visible.iter().map(|(p, _, _)| p.as_path()).collect();
+
+    // Paths dropped by ignore rules never show up in the walk, so look for
+    // them among the children of every directory the walk did see.
+    for (dir, depth, file_type) in &visible {
+        if !file_type.is_some_and(|ft| ft.is_dir()) {
+            continue;
+        }
+        if options.max_depth.is_some_and(|max| *depth >= max) {
+            continue;
+        }
+        for child in fs::read_dir(dir)? {
+            let child = child?.path();
+            if child
+                .file_name()
+                .is_some_and(|n| n == ".git" || options.is_ignore_file(n))
+            {
+                continue;
+            }
+            if !visible_set.contains(child.as_path()) {
+                let rule = explain_ignored(path, &child, child.is_dir(), &global, options);
+                skipped.push(Skipped::new(child, SkipReason::Ignored, rule));
+            }
+        }
+    }
+
+    // Everything else the walk saw but `collect_files` did not was excluded.
+    let mut excluded_dirs: Vec<&Path> = Vec::new();
+    for (entry, _, file_type) in &visible {
+        if entry == path || included.contains(entry.as_path()) {
+            continue;
+        }
+        if excluded_dirs.iter().any(|d| entry.starts_with(d)) {
+            continue;
+        }
+        let is_dir = file_type.is_some_and(|ft| ft.is_dir());
+        let pattern = match user_excludes.matched(entry, is_dir) {
+            Match::Ignore(glob) => Some(format!("matched exclude `{}`", glob.original())),
+            _ => None,
+        };
+        if is_dir {
+            if pattern.is_some() {
+                excluded_dirs.push(entry);
+                skipped.push(Skipped::new(entry.clone(), SkipReason::Excluded, pattern));
+            }
+        } else if file_type.is_some_and(|ft| ft.is_file()) {
+            skipped.push(Skipped::new(entry.clone(), SkipReason::Excluded, pattern));
+        }
+    }
+
+    skipped.sort();
+    Ok(skipped)
+}
+
+/// Reads `path` as text so it can be embedded in the output, or explains why
+/// it has to be skipped: unreadable, larger than `max_size` bytes, or binary.
+pub fn read_text(path: &Path, max_size: Option<u64>) -> std::result::Result<String, Skipped> {
+    let skip = |reason, detail| Skipped::new(path.to_path_buf(), reason, Some(detail));
+
+    if let Some(limit) = max_size {
+        let size = fs::metadata(path)
+            .map_err(|e| skip(SkipReason::Unreadable, e.to_string()))?
+            .len();
+        if size > limit {
+            return Err(skip(
+                SkipReason::TooLarge,
+                format!("{} bytes, limit is {}", size, limit),
+            ));
+        }
+    }
+
+    let bytes = fs::read(path).map_err(|e| skip(SkipReason::Unreadable, e.to_string()))?;
+    match String::from_utf8(bytes) {
+        Ok(contents) if !contents.contains('\0') => Ok(contents),
+        Ok(_) => Err(skip(SkipReason::Binary, "contains NUL bytes".into())),
+        Err(_) => Err(skip(SkipReason::Binary, "not valid UTF-8".into())),
+    }
+}
+
+/// Finds the ignore file rule that hides `path`, searching the ignore files
+/// of its parent directories from the closest one upwards and then the
+/// global gitignore, the same precedence the walker uses.
+fn explain_ignored(
+    root: &Path,
+    path: &Path,
+    is_dir: bool,
+    global: &Gitignore,
+    options: &WalkOptions,
+) -> Option<String> {
+    if options.skip_hidden
+        && path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+    {
+        return Some("hidden".to_string());
+    }
+
+    let mut names: Vec<&str> = options
+        .ignore_filenames
+        .iter()
+        .rev()
+        .map(|n| n.as_str())
+        .collect();
+    names.push(COPYCAT_IGNORE);
+    names.push(".ignore");
+    if options.git_ignore {
+        names.push(".gitignore");
+    }
+
+    for dir in path.ancestors().skip(1) {
+        let repo_root = dir.join(".git").exists();
+        let mut sources: Vec<PathBuf> = names.iter().map(|name| dir.join(name)).collect();
+        if repo_root && options.git_exclude {
+            sources.push(dir.join(".git/info/exclude"));
+        }
+
+        for file in sources {
+            if !file.is_file() {
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add(&file);
+            let Ok(matcher) = builder.build() else {
+                continue;
+            };
+            match matcher.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(glob) => return Some(describe_rule(root, glob)),
+                Match::Whitelist(_) => return None,
+                Match::None => {}
+            }
+        }
+        // Ignore files above the repository root do not apply.
+        if repo_root {
+            break;
+        }
+    }
+
+    match global.matched(path, is_dir) {
+        Match::Ignore(glob) => Some(describe_rule(root, glob)),
+        _ => None,
+    }
+}
+
+/// Describes an ignore rule as "matched `rule` in <file>".
+fn describe_rule(root: &Path, glob: &Glob) -> String {
+    match glob.from() {
+        Some(from) => format!(
+            "matched `{}` in {}",
+            glob.original(),
+            from.strip_prefix(root).unwrap_or(from).display()
+        ),
+        None => format!("matched `{}`", glob.original()),
+    }
+}
+
+/// Builds a matcher for the user's exclude patterns that, unlike an
+/// [`Override`], exposes which pattern matched.
+fn build_exclude_matcher(excludes: &[String], root: &Path) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in excludes {
+        builder.add_line(None, pattern)?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Builds the directory walker shared by [`collect_files`] and
+/// [`collect_skipped`], so both agree on which ignore files apply.
+fn build_walker(path: &Path, overrides: Override, options: &WalkOptions) -> ignore::Walk {
+    let mut builder = WalkBuilder::new(path);
+    builder
+        .standard_filters(true)
+        .hidden(options.skip_hidden)
+        .follow_links(options.follow_links)
+        .ignore(true)
+        .git_ignore(options.git_ignore)
+        .git_exclude(options.git_exclude)
+        .git_global(options.git_global)
+        .max_depth(options.max_depth)
+        .same_file_system(options.same_file_system)
+        .overrides(overrides)
+        .add_custom_ignore_filename(COPYCAT_IGNORE);
+    for name in &options.ignore_filenames {
+        builder.add_custom_ignore_filename(name);
+    }
+    builder.build()
+}
+
+/// Converts a walker error into a readable one, calling out symlink loops
+/// explicitly instead of surfacing the walker's nested error chain.
+fn walk_error(err: ignore::Error) -> anyhow::Error {
+    match find_loop(&err) {
+        Some((ancestor, child)) => anyhow::anyhow!(
+            "symlink loop detected: '{}' points back to '{}' (use --no-follow-links or exclude it)",
+            child.display(),
+            ancestor.display()
+        ),
+        None => err.into(),
+    }
+}
+
+/// Finds a symlink loop error inside a possibly nested walker error.
+fn find_loop(err: &ignore::Error) -> Option<(&Path, &Path)> {
+    match err {
+        ignore::Error::Loop { ancestor, child } => Some((ancestor, child)),
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => find_loop(err),
+        ignore::Error::Partial(errs) => errs.iter().find_map(find_loop),
+        _ => None,
+    }
+}
+
 /// Builds an `Override` set from the given CLI exclude patterns.
 /// By prefixing each pattern with `!`, we tell the override to exclude it.
-fn build_override(excludes: &[String], root: &Path) -> Result<Override> {
+fn build_override(excludes: &[String], root: &Path, options: &WalkOptions) -> Result<Override> {
     let mut builder = OverrideBuilder::new(root);
     builder.add("!.git")?;
+    // copycat's own ignore files configure the copy; they are not part of it.
+    builder.add(&format!("!{}", COPYCAT_IGNORE))?;
+    for name in &options.ignore_filenames {
+        builder.add(&format!("!{}", name))?;
+    }
     for pattern in excludes {
         builder.add(&format!("!{}", pattern))?;
     }
@@ -67,7 +460,7 @@ mod tests {
         let file_path = dir.path().join("hello.txt");
         File::create(&file_path).unwrap();
 
-        let files = collect_files(file_path.clone(), &[], false).unwrap();
+        let files = collect_files(file_path.clone(), &[], false, &WalkOptions::default()).unwrap();
         assert_eq!(files, vec![file_path]);
     }
 
@@ -79,7 +472,13 @@ mod tests {
         File::create(&file1).unwrap();
         File::create(&file2).unwrap();
 
-        let mut files = collect_files(dir.path().to_path_buf(), &[], false).unwrap();
+        let mut files = collect_files(
+            dir.path().to_path_buf(),
+            &[],
+            false,
+            &WalkOptions::default(),
+        )
+        .unwrap();
         files.sort();
         let mut expected = vec![file1, file2];
         expected.sort();
@@ -96,7 +495,13 @@ mod tests {
         File::create(&file2).unwrap();
 
         let excludes = vec!["ignore.me".to_string()];
-        let files = collect_files(dir.path().to_path_buf(), &excludes, false).unwrap();
+        let files = collect_files(
+            dir.path().to_path_buf(),
+            &excludes,
+            false,
+            &WalkOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(files, vec![file1]);
     }
@@ -116,11 +521,69 @@ mod tests {
         File::create(&ignored_file).unwrap();
         File::create(&kept_file).unwrap();
 
-        let mut files = collect_files(dir.path().to_path_buf(), &[], false).unwrap();
+        let mut files = collect_files(
+            dir.path().to_path_buf(),
+            &[],
+            false,
+            &WalkOptions::default(),
+        )
+        .unwrap();
         files.sort();
         assert_eq!(files, vec![gitignore_path.clone(), kept_file]);
     }
 
+    #[test]
+    fn respects_copycatignore() {
+        let dir = tempdir().unwrap();
+
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let copycatignore_path = dir.path().join(".copycatignore");
+        let mut copycatignore = File::create(&copycatignore_path).unwrap();
+        writeln!(copycatignore, "ignored.rs").unwrap();
+
+        let ignored_file = dir.path().join("ignored.rs");
+        let kept_file = dir.path().join("main.rs");
+        File::create(&ignored_file).unwrap();
+        File::create(&kept_file).unwrap();
+
+        let mut files = collect_files(
+            dir.path().to_path_buf(),
+            &[],
+            false,
+            &WalkOptions::default(),
+        )
+        .unwrap();
+        files.sort();
+        assert_eq!(files, vec![kept_file]);
+    }
+
+    #[test]
+    fn respects_nested_copycatignore_outside_git() {
+        let dir = tempdir().unwrap();
+
+        let docs = dir.path().join("docs");
+        fs::create_dir_all(docs.join("translations")).unwrap();
+        let copycatignore_path = docs.join(".copycatignore");
+        let mut copycatignore = File::create(&copycatignore_path).unwrap();
+        writeln!(copycatignore, "translations/").unwrap();
+
+        File::create(docs.join("translations/de.md")).unwrap();
+        let kept_doc = docs.join("index.md");
+        File::create(&kept_doc).unwrap();
+
+        let root = dir.path().to_path_buf();
+        let files = collect_files(root.clone(), &[], true, &WalkOptions::default()).unwrap();
+        assert_eq!(files, vec![kept_doc]);
+
+        let skipped = collect_skipped(&root, &[], &files, &WalkOptions::default()).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            skipped[0].detail.as_deref(),
+            Some("matched `translations/` in docs/.copycatignore")
+        );
+    }
+
     #[test]
     fn respects_combined_gitignore_and_excludes() {
         let dir = tempdir().unwrap();
@@ -140,7 +603,13 @@ mod tests {
         File::create(&file_kept).unwrap();
 
         let excludes = vec!["excluded_by_flag.txt".to_string()];
-        let mut files = collect_files(dir.path().to_path_buf(), &excludes, false).unwrap();
+        let mut files = collect_files(
+            dir.path().to_path_buf(),
+            &excludes,
+            false,
+            &WalkOptions::default(),
+        )
+        .unwrap();
         files.sort();
 
         assert_eq!(files, vec![gitignore_path.clone(), file_kept]);
@@ -162,7 +631,13 @@ mod tests {
         File::create(&allowed_file).unwrap();
 
         let excludes = vec!["**/*.log".to_string()];
-        let files = collect_files(dir.path().to_path_buf(), &excludes, true).unwrap();
+        let files = collect_files(
+            dir.path().to_path_buf(),
+            &excludes,
+            true,
+            &WalkOptions::default(),
+        )
+        .unwrap();
 
         assert_eq!(files, vec![allowed_file]);
     }
@@ -175,7 +650,8 @@ mod tests {
         File::create(&file_a).unwrap();
         File::create(&file_b).unwrap();
 
-        let files = collect_files(dir.path().to_path_buf(), &[], true).unwrap();
+        let files =
+            collect_files(dir.path().to_path_buf(), &[], true, &WalkOptions::default()).unwrap();
 
         assert_eq!(files, vec![file_b, file_a]);
     }
@@ -190,7 +666,8 @@ mod tests {
         File::create(&hidden_file).unwrap();
         File::create(&hidden_dir_file).unwrap();
 
-        let mut files = collect_files(dir.path().to_path_buf(), &[], true).unwrap();
+        let mut files =
+            collect_files(dir.path().to_path_buf(), &[], true, &WalkOptions::default()).unwrap();
         files.sort();
         let expected = vec![hidden_dir_file.clone(), hidden_file.clone()];
         assert_eq!(files, expected);
@@ -206,7 +683,279 @@ mod tests {
         let file = dir.path().join("main.rs");
         File::create(&file).unwrap();
 
-        let files = collect_files(dir.path().to_path_buf(), &[], true).unwrap();
+        let files =
+            collect_files(dir.path().to_path_buf(), &[], true, &WalkOptions::default()).unwrap();
         assert_eq!(files, vec![file]);
     }
+
+    #[test]
+    fn collects_empty_dirs_but_not_ignored_ones() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let mut gitignore = File::create(dir.path().join(".gitignore")).unwrap();
+        writeln!(gitignore, "build/").unwrap();
+
+        let empty = dir.path().join("empty");
+        let src = dir.path().join("src");
+        fs::create_dir(&empty).unwrap();
+        fs::create_dir(&src).unwrap();
+        fs::create_dir(dir.path().join("build")).unwrap();
+        fs::create_dir(dir.path().join("tmp")).unwrap();
+        File::create(src.join("main.rs")).unwrap();
+
+        let mut dirs =
+            collect_dirs(dir.path(), &["tmp".to_string()], &WalkOptions::default()).unwrap();
+        dirs.sort();
+        assert_eq!(dirs, vec![empty, src]);
+    }
+
+    #[test]
+    fn reports_ignored_and_excluded_paths() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let mut gitignore = File::create(dir.path().join(".gitignore")).unwrap();
+        writeln!(gitignore, "target/").unwrap();
+        writeln!(gitignore, "secret.env").unwrap();
+
+        let target = dir.path().join("target");
+        fs::create_dir_all(target.join("debug")).unwrap();
+        File::create(target.join("debug/app")).unwrap();
+        let secret = dir.path().join("secret.env");
+        File::create(&secret).unwrap();
+
+        let logs = dir.path().join("logs");
+        fs::create_dir(&logs).unwrap();
+        let log = logs.join("error.log");
+        File::create(&log).unwrap();
+        let docs = dir.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        File::create(docs.join("guide.md")).unwrap();
+        File::create(dir.path().join("main.rs")).unwrap();
+
+        let root = dir.path().to_path_buf();
+        let excludes = vec!["**/*.log".to_string(), "docs".to_string()];
+        let files = collect_files(root.clone(), &excludes, true, &WalkOptions::default()).unwrap();
+        let skipped = collect_skipped(&root, &excludes, &files, &WalkOptions::default()).unwrap();
+
+        let reasons: Vec<(PathBuf, SkipReason)> =
+            skipped.iter().map(|s| (s.path.clone(), s.reason)).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (docs, SkipReason::Excluded),
+                (log, SkipReason::Excluded),
+                (secret, SkipReason::Ignored),
+                (target, SkipReason::Ignored),
+            ]
+        );
+
+        let details: Vec<&str> = skipped
+            .iter()
+            .map(|s| s.detail.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                "matched exclude `docs`",
+                "matched exclude `**/*.log`",
+                "matched `secret.env` in .gitignore",
+                "matched `target/` in .gitignore",
+            ]
+        );
+    }
+
+    #[test]
+    fn explains_rule_from_nested_ignore_file() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let mut gitignore = File::create(dir.path().join(".gitignore")).unwrap();
+        writeln!(gitignore, "*.tmp").unwrap();
+
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        let mut nested = File::create(sub.join(".gitignore")).unwrap();
+        writeln!(nested, "fixtures/").unwrap();
+        fs::create_dir(sub.join("fixtures")).unwrap();
+        File::create(sub.join("fixtures/big.json")).unwrap();
+        File::create(sub.join("scratch.tmp")).unwrap();
+        File::create(sub.join("lib.rs")).unwrap();
+
+        let root = dir.path().to_path_buf();
+        let files = collect_files(root.clone(), &[], true, &WalkOptions::default()).unwrap();
+        let skipped = collect_skipped(&root, &[], &files, &WalkOptions::default()).unwrap();
+
+        let details: Vec<(&Path, &str)> = skipped
+            .iter()
+            .map(|s| (s.path.as_path(), s.detail.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                (
+                    sub.join("fixtures").as_path(),
+                    "matched `fixtures/` in sub/.gitignore"
+                ),
+                (
+                    sub.join("scratch.tmp").as_path(),
+                    "matched `*.tmp` in .gitignore"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_text_classifies_files() {
+        let dir = tempdir().unwrap();
+
+        let text = dir.path().join("main.rs");
+        fs::write(&text, "fn main() {}\n").unwrap();
+        let binary = dir.path().join("logo.png");
+        fs::write(&binary, [0x89, b'P', 0x00, 0xff]).unwrap();
+        let missing = dir.path().join("missing.rs");
+
+        assert_eq!(read_text(&text, None).unwrap(), "fn main() {}\n");
+        assert_eq!(
+            read_text(&text, Some(4)).unwrap_err().reason,
+            SkipReason::TooLarge
+        );
+        assert_eq!(
+            read_text(&binary, None).unwrap_err().reason,
+            SkipReason::Binary
+        );
+        assert_eq!(
+            read_text(&missing, None).unwrap_err().reason,
+            SkipReason::Unreadable
+        );
+    }
+
+    #[test]
+    fn reports_nothing_for_single_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("hello.txt");
+        File::create(&file_path).unwrap();
+
+        let files = collect_files(file_path.clone(), &[], false, &WalkOptions::default()).unwrap();
+        assert!(
+            collect_skipped(&file_path, &[], &files, &WalkOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn skips_hidden_files_when_requested() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".hidden")).unwrap();
+        File::create(dir.path().join(".hidden/inside.txt")).unwrap();
+        File::create(dir.path().join(".secret")).unwrap();
+        let visible = dir.path().join("main.rs");
+        File::create(&visible).unwrap();
+
+        let options = WalkOptions {
+            skip_hidden: true,
+            ..Default::default()
+        };
+        let root = dir.path().to_path_buf();
+        let files = collect_files(root.clone(), &[], true, &options).unwrap();
+        assert_eq!(files, vec![visible]);
+
+        let skipped = collect_skipped(&root, &[], &files, &options).unwrap();
+        assert!(skipped
+            .iter()
+            .all(|s| s.detail.as_deref() == Some("hidden")));
+        assert_eq!(skipped.len(), 2);
+    }
+
+    #[test]
+    fn limits_walk_depth() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        let top = dir.path().join("top.rs");
+        let one = dir.path().join("a/one.rs");
+        File::create(&top).unwrap();
+        File::create(&one).unwrap();
+        File::create(dir.path().join("a/b/two.rs")).unwrap();
+
+        let options = WalkOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let root = dir.path().to_path_buf();
+        let files = collect_files(root.clone(), &[], true, &options).unwrap();
+        assert_eq!(files, vec![one, top]);
+
+        // Entries beyond the depth limit are not reported as ignored.
+        let skipped = collect_skipped(&root, &[], &files, &options).unwrap();
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn respects_custom_ignore_filenames() {
+        let dir = tempdir().unwrap();
+        let mut ignore_file = File::create(dir.path().join(".llmignore")).unwrap();
+        writeln!(ignore_file, "fixtures/").unwrap();
+        fs::create_dir(dir.path().join("fixtures")).unwrap();
+        File::create(dir.path().join("fixtures/big.json")).unwrap();
+        let kept = dir.path().join("main.rs");
+        File::create(&kept).unwrap();
+
+        let options = WalkOptions {
+            ignore_filenames: vec![".llmignore".to_string()],
+            ..Default::default()
+        };
+        let root = dir.path().to_path_buf();
+        let files = collect_files(root.clone(), &[], true, &options).unwrap();
+        assert_eq!(files, vec![kept]);
+
+        let skipped = collect_skipped(&root, &[], &files, &options).unwrap();
+        let fixtures = skipped
+            .iter()
+            .find(|s| s.path.ends_with("fixtures"))
+            .unwrap();
+        assert_eq!(
+            fixtures.detail.as_deref(),
+            Some("matched `fixtures/` in .llmignore")
+        );
+    }
+
+    #[test]
+    fn ignores_gitignore_when_disabled() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+        let gitignore_path = dir.path().join(".gitignore");
+        let mut gitignore = File::create(&gitignore_path).unwrap();
+        writeln!(gitignore, "ignored.rs").unwrap();
+        let ignored_file = dir.path().join("ignored.rs");
+        File::create(&ignored_file).unwrap();
+
+        let options = WalkOptions {
+            git_ignore: false,
+            ..Default::default()
+        };
+        let files = collect_files(dir.path().to_path_buf(), &[], true, &options).unwrap();
+        assert_eq!(files, vec![gitignore_path, ignored_file]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_symlink_loops() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        File::create(sub.join("main.rs")).unwrap();
+        std::os::unix::fs::symlink(&sub, sub.join("again")).unwrap();
+
+        let err = collect_files(dir.path().to_path_buf(), &[], true, &WalkOptions::default())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("symlink loop detected"));
+
+        let options = WalkOptions {
+            follow_links: false,
+            ..Default::default()
+        };
+        let files = collect_files(dir.path().to_path_buf(), &[], true, &options).unwrap();
+        assert_eq!(files, vec![sub.join("main.rs")]);
+    }
 }
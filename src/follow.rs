@@ -0,0 +1,588 @@
+use anyhow::{anyhow, bail, Result};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::{Language, Node, Parser};
+
+use crate::language::detect_language;
+
+/// Extensions tried, in order, for extension-less JS/TS import specifiers.
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts"];
+
+/// Where to start following imports and how far.
+#[derive(Debug, Clone)]
+pub struct Follow {
+    /// The entry file.
+    pub entry: PathBuf,
+    /// Follow imports at most this many levels below the entry.
+    pub max_depth: Option<usize>,
+}
+
+/// Select the files `follow.entry` pulls in, transitively, from `files`.
+///
+/// Rust `mod`/`use crate::…`, JS/TS `import`/`export … from`/`require()` and
+/// Python `import`/`from … import` statements are resolved to local files.
+/// Only paths in `files` are followed, so ignore rules and excludes still
+/// apply. The result is in dependency order: every file comes after the
+/// files it imports, and the entry comes last.
+pub fn follow_imports(follow: &Follow, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let local: HashMap<PathBuf, &PathBuf> = files.iter().map(|f| (normalize(f), f)).collect();
+    let entry = normalize(&follow.entry);
+    if !local.contains_key(&entry) {
+        bail!(
+            "entry file {} is not among the collected files",
+            follow.entry.display()
+        );
+    }
+
+    // Breadth-first so every file is expanded at its shallowest depth.
+    let mut edges: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut seen = HashSet::from([entry.clone()]);
+    let mut queue = VecDeque::from([(entry.clone(), 0)]);
+    while let Some((file, depth)) = queue.pop_front() {
+        if follow.max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        let source = fs::read_to_string(&file)
+            .map_err(|e| anyhow!("failed to read {}: {}", file.display(), e))?;
+        let deps: Vec<PathBuf> = imports(&file, &source, &|p| local.contains_key(p));
+        for dep in &deps {
+            if seen.insert(dep.clone()) {
+                queue.push_back((dep.clone(), depth + 1));
+            }
+        }
+        edges.insert(file, deps);
+    }
+
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    post_order(&entry, &edges, &mut visited, &mut order);
+    Ok(order.into_iter().map(|p| local[&p].clone()).collect())
+}
+
+/// Append `file` to `order` after everything it imports.
+fn post_order(
+    file: &PathBuf,
+    edges: &HashMap<PathBuf, Vec<PathBuf>>,
+    visited: &mut HashSet<PathBuf>,
+    order: &mut Vec<PathBuf>,
+) {
+    if !visited.insert(file.clone()) {
+        return;
+    }
+    for dep in edges.get(file).into_iter().flatten() {
+        post_order(dep, edges, visited, order);
+    }
+    order.push(file.clone());
+}
+
+/// Make `path` absolute and resolve `.` and `..` lexically, so the same file
+/// reached through different relative paths compares equal.
+fn normalize(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut out = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// The file whose imports are resolved, with the paths they are resolved
+/// against computed once rather than for every syntax node.
+struct Importer {
+    /// The normalized path of the file.
+    file: PathBuf,
+    /// Its directory.
+    dir: PathBuf,
+    /// Rust: the directory holding its child modules.
+    module_dir: PathBuf,
+    /// Rust: the crate root, looked up on the first `use crate::…`.
+    crate_root: OnceCell<PathBuf>,
+}
+
+impl Importer {
+    fn new(file: &Path) -> Self {
+        let file = normalize(file);
+        Importer {
+            dir: file.parent().unwrap_or(Path::new("")).to_path_buf(),
+            module_dir: rust_module_dir(&file),
+            crate_root: OnceCell::new(),
+            file,
+        }
+    }
+
+    fn crate_root(&self) -> &Path {
+        self.crate_root.get_or_init(|| rust_crate_root(&self.file))
+    }
+}
+
+/// The local files imported by `file`, in source order; `exists` tells
+/// whether a normalized candidate path is a local file.
+fn imports(file: &Path, source: &str, exists: &dyn Fn(&Path) -> bool) -> Vec<PathBuf> {
+    let language = detect_language(file);
+    let grammar: Language = match language {
+        "rust" => tree_sitter_rust::LANGUAGE.into(),
+        "python" => tree_sitter_python::LANGUAGE.into(),
+        "typescript" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        "javascript" | "jsx" => tree_sitter_javascript::LANGUAGE.into(),
+        _ => return Vec::new(),
+    };
+    let mut parser = Parser::new();
+    if parser.set_language(&grammar).is_err() {
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(source, None) else {
+        return Vec::new();
+    };
+
+    let importer = Importer::new(file);
+    let mut found = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let candidates = match language {
+            "rust" => rust_imports(&importer, node, source),
+            "python" => python_imports(&importer, node, source),
+            _ => js_imports(&importer, node, source),
+        };
+        for group in candidates {
+            if let Some(hit) = group.into_iter().find(|c| exists(c)) {
+                if hit != importer.file && !found.contains(&hit) {
+                    found.push(hit);
+                }
+            }
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'_>> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    found
+}
+
+/// Text of `node` in `source`.
+fn text<'a>(node: Node<'_>, source: &'a str) -> &'a str {
+    &source[node.byte_range()]
+}
+
+/// Candidate files for a Rust `mod name;` or `use crate::…` at `node`: one
+/// group per imported path, most specific candidate first.
+fn rust_imports(importer: &Importer, node: Node<'_>, source: &str) -> Vec<Vec<PathBuf>> {
+    match node.kind() {
+        "mod_item" if node.child_by_field_name("body").is_none() => {
+            let Some(name) = node.child_by_field_name("name") else {
+                return Vec::new();
+            };
+            vec![rust_module_files(
+                &importer.module_dir,
+                &[text(name, source)],
+            )]
+        }
+        "use_declaration" => {
+            let Some(argument) = node.child_by_field_name("argument") else {
+                return Vec::new();
+            };
+            let mut groups = Vec::new();
+            for path in expand_use_tree(text(argument, source)) {
+                let mut segments: Vec<&str> = path.split("::").collect();
+                let mut base = match segments[0] {
+                    "crate" => {
+                        segments.remove(0);
+                        importer.crate_root().to_path_buf()
+                    }
+                    "self" | "super" => importer.module_dir.clone(),
+                    _ => continue,
+                };
+                while segments.first() == Some(&"super") {
+                    segments.remove(0);
+                    base.pop();
+                }
+                segments.retain(|s| *s != "self" && *s != "*");
+                groups.push(
+                    (1..=segments.len())
+                        .rev()
+                        .flat_map(|len| rust_module_files(&base, &segments[..len]))
+                        .collect(),
+                );
+            }
+            groups
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// `base/a/b.rs` and `base/a/b/mod.rs` for module path `a::b`.
+fn rust_module_files(base: &Path, segments: &[&str]) -> Vec<PathBuf> {
+    let dir = segments
+        .iter()
+        .fold(base.to_path_buf(), |dir, s| dir.join(s));
+    vec![dir.with_extension("rs"), dir.join("mod.rs")]
+}
+
+/// The directory holding the child modules of `file`: its own directory for
+/// `mod.rs`, `lib.rs` and `main.rs`, otherwise a directory named after it.
+fn rust_module_dir(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new("")).to_path_buf();
+    match file.file_stem().and_then(|s| s.to_str()) {
+        Some("mod" | "lib" | "main") | None => parent,
+        Some(stem) => parent.join(stem),
+    }
+}
+
+/// The nearest ancestor directory of `file` with a `lib.rs` or `main.rs`.
+fn rust_crate_root(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new(""));
+    parent
+        .ancestors()
+        .find(|dir| dir.join("lib.rs").is_file() || dir.join("main.rs").is_file())
+        .unwrap_or(parent)
+        .to_path_buf()
+}
+
+/// Flatten a Rust use tree such as `crate::a::{b, c::{self, D as E}}` into
+/// plain paths like `crate::a::b`, dropping aliases.
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let mut tokens = tree.split_whitespace().peekable();
+    let mut compact = String::new();
+    while let Some(token) = tokens.next() {
+        if token == "as" {
+            // Drop the alias but keep the punctuation after it.
+            let alias = tokens.next().unwrap_or("");
+            compact.push_str(alias.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_'));
+        } else {
+            compact.push_str(token);
+        }
+    }
+
+    let Some(open) = compact.find('{') else {
+        return vec![compact];
+    };
+    let Some(close) = compact.rfind('}') else {
+        return vec![compact];
+    };
+    let prefix = &compact[..open];
+
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (i, c) in compact[..close].char_indices().skip(open + 1) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&compact[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&compact[start..close]);
+
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| expand_use_tree(&format!("{}{}", prefix, part)))
+        .collect()
+}
+
+/// Candidate files for a Python `import` or `from … import` at `node`, one
+/// group per imported name.
+fn python_imports(importer: &Importer, node: Node<'_>, source: &str) -> Vec<Vec<PathBuf>> {
+    let dir = &importer.dir;
+    let module_name = |n: Node<'_>| match n.kind() {
+        "aliased_import" => n.child_by_field_name("name").map(|n| text(n, source)),
+        "dotted_name" => Some(text(n, source)),
+        _ => None,
+    };
+
+    match node.kind() {
+        "import_statement" => {
+            let mut cursor = node.walk();
+            node.children_by_field_name("name", &mut cursor)
+                .filter_map(module_name)
+                .map(|module| python_absolute(dir, module))
+                .collect()
+        }
+        "import_from_statement" => {
+            let Some(module) = node.child_by_field_name("module_name") else {
+                return Vec::new();
+            };
+            let module = text(module, source);
+            let dots = module.len() - module.trim_start_matches('.').len();
+            let bases: Vec<PathBuf> = if dots > 0 {
+                let mut base = dir.clone();
+                for _ in 1..dots {
+                    base.pop();
+                }
+                vec![base]
+            } else {
+                dir.ancestors().map(Path::to_path_buf).collect()
+            };
+            let module = &module[dots..];
+
+            let mut cursor = node.walk();
+            let mut names: Vec<Option<&str>> = node
+                .children_by_field_name("name", &mut cursor)
+                .filter_map(module_name)
+                .map(Some)
+                .collect();
+            if names.is_empty() {
+                // `from pkg import *`
+                names.push(None);
+            }
+
+            let mut groups = Vec::new();
+            for name in names {
+                let mut group = Vec::new();
+                for base in &bases {
+                    let package = python_segments(module)
+                        .fold(base.clone(), |dir, segment| dir.join(segment));
+                    // `from pkg import sub` may name a submodule.
+                    if let Some(name) = name {
+                        group.extend(python_module_files(&package, name));
+                    }
+                    if module.is_empty() {
+                        group.push(package.join("__init__.py"));
+                    } else {
+                        group.extend(python_module_files(base, module));
+                    }
+                }
+                groups.push(group);
+            }
+            groups
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Candidates for absolute module `a.b`, looked up from `dir` and each of
+/// its ancestors.
+fn python_absolute(dir: &Path, module: &str) -> Vec<PathBuf> {
+    dir.ancestors()
+        .flat_map(|base| python_module_files(base, module))
+        .collect()
+}
+
+/// `base/a/b.py` and `base/a/b/__init__.py` for module `a.b`.
+fn python_module_files(base: &Path, module: &str) -> Vec<PathBuf> {
+    let dir = python_segments(module).fold(base.to_path_buf(), |dir, s| dir.join(s));
+    vec![dir.with_extension("py"), dir.join("__init__.py")]
+}
+
+fn python_segments(module: &str) -> impl Iterator<Item = &str> {
+    module.split('.').filter(|s| !s.is_empty())
+}
+
+/// Candidate files for a relative JS/TS `import`, `export … from`,
+/// `require()` or dynamic `import()` at `node`.
+fn js_imports(importer: &Importer, node: Node<'_>, source: &str) -> Vec<Vec<PathBuf>> {
+    let specifier = match node.kind() {
+        "import_statement" | "export_statement" => node.child_by_field_name("source"),
+        "call_expression" => {
+            let is_import = node
+                .child_by_field_name("function")
+                .is_some_and(|f| matches!(text(f, source), "require" | "import"));
+            node.child_by_field_name("arguments")
+                .and_then(|args| args.named_child(0))
+                .filter(|arg| is_import && arg.kind() == "string")
+        }
+        _ => None,
+    };
+    let Some(specifier) = specifier else {
+        return Vec::new();
+    };
+    let specifier = text(specifier, source).trim_matches(['"', '\'', '`']);
+    if !specifier.starts_with("./") && !specifier.starts_with("../") {
+        return Vec::new();
+    }
+
+    let dir = &importer.dir;
+    let target = normalize(&dir.join(specifier));
+    let mut candidates = vec![target.clone()];
+    // TypeScript ESM imports name the emitted `.js` file.
+    if let Some(stem) = specifier.strip_suffix(".js") {
+        let stem = normalize(&dir.join(stem));
+        candidates.extend(["ts", "tsx"].map(|ext| stem.with_extension(ext)));
+    }
+    for ext in JS_EXTENSIONS {
+        let mut with_ext = target.clone().into_os_string();
+        with_ext.push(".");
+        with_ext.push(ext);
+        candidates.push(with_ext.into());
+    }
+    candidates.extend(
+        JS_EXTENSIONS
+            .iter()
+            .map(|ext| target.join(format!("index.{}", ext))),
+    );
+    vec![candidates]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write(root: &Path, rel: &str, contents: &str) -> PathBuf {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn follow(
+        root: &Path,
+        entry: &str,
+        files: &[PathBuf],
+        max_depth: Option<usize>,
+    ) -> Vec<String> {
+        let follow = Follow {
+            entry: root.join(entry),
+            max_depth,
+        };
+        follow_imports(&follow, files)
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_follow_rust_mods_and_uses() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            write(root, "src/main.rs", "mod cli;\nmod util;\nfn main() {}\n"),
+            write(
+                root,
+                "src/cli.rs",
+                "use crate::net::{client::Client, self};\n",
+            ),
+            write(root, "src/net/mod.rs", "pub mod client;\n"),
+            write(
+                root,
+                "src/net/client.rs",
+                "use super::super::util::helper;\n",
+            ),
+            write(root, "src/util.rs", "pub fn helper() {}\n"),
+            write(root, "src/unused.rs", "// not reachable\n"),
+        ];
+
+        assert_eq!(
+            follow(root, "src/main.rs", &files, None),
+            vec![
+                "src/util.rs",
+                "src/net/client.rs",
+                "src/net/mod.rs",
+                "src/cli.rs",
+                "src/main.rs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_js_and_ts_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            write(
+                root,
+                "src/index.ts",
+                "import { a } from './a';\nimport b from \"./lib\";\nimport React from 'react';\n\
+                 import { View } from './view';\n",
+            ),
+            write(root, "src/a.ts", "export * from '../shared/types.js';\n"),
+            write(
+                root,
+                "src/view.tsx",
+                "export const View = () => <div />;\nimport { a } from './a';\n",
+            ),
+            write(root, "src/lib/index.js", "const c = require('./c');\n"),
+            write(root, "src/lib/c.js", "module.exports = 1;\n"),
+            write(root, "shared/types.ts", "export type T = number;\n"),
+        ];
+
+        assert_eq!(
+            follow(root, "src/index.ts", &files, None),
+            vec![
+                "shared/types.ts",
+                "src/a.ts",
+                "src/lib/c.js",
+                "src/lib/index.js",
+                "src/view.tsx",
+                "src/index.ts"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follow_python_imports() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            write(
+                root,
+                "app.py",
+                "import pkg.models\nfrom pkg import views\nimport os\n",
+            ),
+            write(root, "pkg/__init__.py", ""),
+            write(root, "pkg/models.py", "from .base import Base\n"),
+            write(root, "pkg/base.py", "# '''import nothing'''\n"),
+            write(root, "pkg/views.py", "from . import models\n"),
+        ];
+
+        assert_eq!(
+            follow(root, "app.py", &files, None),
+            vec!["pkg/base.py", "pkg/models.py", "pkg/views.py", "app.py"]
+        );
+    }
+
+    #[test]
+    fn test_follow_depth_limit_and_cycles() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            write(root, "a.py", "import b\n"),
+            write(root, "b.py", "import c\nimport a\n"),
+            write(root, "c.py", "import a\n"),
+        ];
+
+        assert_eq!(follow(root, "a.py", &files, Some(1)), vec!["b.py", "a.py"]);
+        assert_eq!(
+            follow(root, "a.py", &files, None),
+            vec!["c.py", "b.py", "a.py"]
+        );
+    }
+
+    #[test]
+    fn test_follow_only_collected_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        let main = write(root, "main.py", "import secret\n");
+        write(root, "secret.py", "KEY = 1\n");
+
+        let files = vec![main];
+        assert_eq!(follow(root, "main.py", &files, None), vec!["main.py"]);
+
+        let missing = Follow {
+            entry: root.join("secret.py"),
+            max_depth: None,
+        };
+        assert!(follow_imports(&missing, &files).is_err());
+    }
+
+    #[test]
+    fn test_expand_use_tree() {
+        assert_eq!(
+            expand_use_tree("crate::a::{b, c::{self, D as E}}"),
+            vec!["crate::a::b", "crate::a::c::self", "crate::a::c::D"]
+        );
+        assert_eq!(expand_use_tree("std::io"), vec!["std::io"]);
+    }
+}
@@ -1,8 +1,37 @@
 use anyhow::Result;
-use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::language::detect_language;
+use crate::files::{read_text, SkipReason};
+use crate::grep::{region_text, GrepOptions};
+use crate::language::detect_language_of;
+use crate::notebook::{render_notebook, NotebookOptions};
+use crate::outline::outline;
+use crate::sample::{sample_data, SampleOptions};
+use crate::schema::summarize_structure;
+use crate::stats::{format_bytes, Summary};
+use crate::transform::{transform, TransformOptions};
+
+/// Options controlling which files are embedded and how.
+#[derive(Debug, Default, Clone)]
+pub struct FormatOptions {
+    /// Skip files larger than this many bytes.
+    pub max_size: Option<u64>,
+    /// Reduce supported source files to their outline, see
+    /// [`crate::outline::outline`]; other files are embedded in full.
+    pub outline: bool,
+    /// Comment stripping and whitespace compaction applied after reading.
+    pub transform: TransformOptions,
+    /// Content filter; when it has a context size, only the matching
+    /// regions of each file are embedded.
+    pub grep: Option<GrepOptions>,
+    /// How `.ipynb` notebooks are rendered.
+    pub notebook: NotebookOptions,
+    /// How CSV, TSV and JSON Lines files are sampled.
+    pub sample: SampleOptions,
+    /// Embed JSON and YAML files larger than this many bytes as a summary
+    /// of their structure, see [`crate::schema::summarize_structure`].
+    pub summarize_over: Option<u64>,
+}
 
 /// Generates a Markdown string by reading the contents of the given files
 /// and formatting each one within a Markdown heading and optional code fence.
@@ -11,39 +40,129 @@ use crate::language::detect_language;
 ///
 /// * `root` - The base path used to determine relative file paths in the Markdown output.
 /// * `files` - A slice of file paths to process and include in the generated Markdown.
+/// * `options` - Limits and rendering options, see [`FormatOptions`].
+/// * `summary` - Collects size metrics and content hashes for included files
+///   and records binary, oversized or unreadable files that had to be skipped.
 ///
 /// # Returns
 ///
 /// A `Result<String>` containing the full Markdown representation of all files,
 /// or an error if file reading fails.
-pub fn generate_markdown(root: &Path, files: &[PathBuf]) -> Result<String> {
+pub fn generate_markdown(
+    root: &Path,
+    files: &[PathBuf],
+    options: &FormatOptions,
+    summary: &mut Summary,
+) -> Result<String> {
     let mut output = String::new();
 
     for file in files {
         let rel_path = file.strip_prefix(root).unwrap_or(file.as_path());
-        let contents = match fs::read_to_string(file) {
-            Ok(c) => c,
-            Err(e) => {
-                eprintln!(
-                    "warning: could not read file '{}': {}",
-                    rel_path.display(),
-                    e
-                );
+        let contents = match read_text(file, options.max_size) {
+            Ok(c) => {
+                summary.manifest.add(root, file, c.as_bytes());
+                c
+            }
+            Err(skip) => {
+                if skip.reason == SkipReason::Unreadable {
+                    eprintln!(
+                        "warning: could not read file '{}': {}",
+                        rel_path.display(),
+                        skip.detail.unwrap_or_default()
+                    );
+                }
+                summary.record_skip(rel_path, skip.reason);
                 continue;
             }
         };
 
-        let language = detect_language(file);
+        let language = detect_language_of(file, &contents);
+        let language = language.as_str();
+        // Notebooks are rendered as Markdown with their own code blocks, or
+        // embedded as JSON when they cannot be parsed; large JSON and YAML
+        // documents are reduced to their structure and data files to a
+        // sample and their shape.
+        let summarize = options
+            .summarize_over
+            .is_some_and(|limit| contents.len() as u64 > limit);
+        let mut title = format!("`{}`", rel_path.display());
+        let rendered = match language {
+            "jupyter" => render_notebook(&contents, &options.notebook, &options.transform).ok(),
+            "json" | "yaml" if summarize => {
+                let summary = summarize_structure(&contents, language);
+                if summary.is_some() {
+                    title.push_str(&format!(
+                        " (structure summary of {})",
+                        format_bytes(contents.len() as u64)
+                    ));
+                }
+                summary
+            }
+            _ => options
+                .sample
+                .rows_for(rel_path)
+                .and_then(|rows| sample_data(&contents, language, rows)),
+        };
+        let is_rendered = rendered.is_some();
+        let (contents, block) = match rendered {
+            Some(rendered) => (rendered, "markdown"),
+            None if language == "jupyter" => (contents, "json"),
+            None => {
+                let contents = if options.transform.is_enabled() {
+                    transform(&contents, language, &options.transform)
+                } else {
+                    contents
+                };
+                let contents = match options.outline.then(|| outline(&contents, language)) {
+                    Some(Some(outlined)) => outlined,
+                    _ => contents,
+                };
+                (contents, language)
+            }
+        };
+        // With `--grep-context`, only the matching regions are embedded,
+        // each under a heading with its line range. Rendered notebooks,
+        // samples and summaries have no lines of the file to point at, so
+        // they are embedded whole.
+        let (sections, whole): (Vec<(String, String)>, bool) = match options
+            .grep
+            .as_ref()
+            .filter(|_| !is_rendered)
+            .and_then(|grep| Some(grep.regions(&contents, grep.context?)))
+        {
+            Some(regions) if !regions.is_empty() => (
+                regions
+                    .iter()
+                    .map(|range| {
+                        let heading = format!(
+                            "`{}` (lines {}-{})",
+                            rel_path.display(),
+                            range.start(),
+                            range.end()
+                        );
+                        (heading, region_text(&contents, range))
+                    })
+                    .collect(),
+                false,
+            ),
+            _ => (vec![(title, contents)], true),
+        };
+
+        let included: Vec<&str> = sections.iter().map(|(_, text)| text.as_str()).collect();
+        summary.record_file(rel_path, language, &included.join("\n"));
 
-        // Add a section heading with the relative path
-        output.push_str(&format!("### `{}`\n\n", rel_path.display()));
+        for (heading, text) in &sections {
+            // Add a section heading with the relative path
+            output.push_str(&format!("### {}\n\n", heading));
 
-        // For Markdown files, embed directly; otherwise, fence code blocks
-        if language == "markdown" {
-            output.push_str(&contents);
-            output.push_str("\n\n");
-        } else {
-            output.push_str(&format!("```{}\n{}\n```\n\n", language, contents));
+            // For whole Markdown files, embed directly; otherwise, fence
+            // code blocks and regions
+            if block == "markdown" && whole {
+                output.push_str(text);
+                output.push_str("\n\n");
+            } else {
+                output.push_str(&format!("```{}\n{}\n```\n\n", block, text));
+            }
         }
     }
 
@@ -53,7 +172,7 @@ pub fn generate_markdown(root: &Path, files: &[PathBuf]) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
+    use std::fs::{self, File};
     use std::io::Write;
     use tempfile::tempdir; // Add dev-dependency: `tempfile = "3.5"`
 
@@ -68,7 +187,13 @@ mod tests {
         writeln!(file, "fn main() {{ println!(\"Hello\"); }}").unwrap();
 
         let files = vec![file_path.clone()];
-        let md_output = generate_markdown(root, &files).unwrap();
+        let md_output = generate_markdown(
+            root,
+            &files,
+            &FormatOptions::default(),
+            &mut Summary::default(),
+        )
+        .unwrap();
 
         // Check some aspects of the output
         assert!(md_output.contains("### `hello.rs`"));
@@ -86,7 +211,13 @@ mod tests {
         writeln!(file, "# Hello Markdown").unwrap();
 
         let files = vec![file_path.clone()];
-        let md_output = generate_markdown(root, &files).unwrap();
+        let md_output = generate_markdown(
+            root,
+            &files,
+            &FormatOptions::default(),
+            &mut Summary::default(),
+        )
+        .unwrap();
 
         // Should not have triple backticks
         assert!(md_output.contains("### `README.md`"));
@@ -101,7 +232,13 @@ mod tests {
 
         // No files
         let files: Vec<PathBuf> = vec![];
-        let md_output = generate_markdown(root, &files).unwrap();
+        let md_output = generate_markdown(
+            root,
+            &files,
+            &FormatOptions::default(),
+            &mut Summary::default(),
+        )
+        .unwrap();
 
         // Should be empty, no headings
         assert!(md_output.is_empty());
@@ -119,9 +256,279 @@ mod tests {
         let missing_path = root.join("missing.rs");
 
         let files = vec![ok_path.clone(), missing_path.clone()];
-        let md_output = generate_markdown(root, &files).unwrap();
+        let md_output = generate_markdown(
+            root,
+            &files,
+            &FormatOptions::default(),
+            &mut Summary::default(),
+        )
+        .unwrap();
 
         assert!(md_output.contains("### `ok.rs`"));
         assert!(!md_output.contains("### `missing.rs`"));
     }
+
+    #[test]
+    fn test_generate_markdown_records_summary() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let ok_path = root.join("ok.rs");
+        let mut ok_file = File::create(&ok_path).unwrap();
+        writeln!(ok_file, "fn main() {{}}").unwrap();
+
+        let bin_path = root.join("logo.png");
+        let mut bin_file = File::create(&bin_path).unwrap();
+        bin_file
+            .write_all(&[0x89, b'P', b'N', b'G', 0x00, 0xff])
+            .unwrap();
+
+        let missing_path = root.join("missing.rs");
+
+        let files = vec![ok_path, bin_path, missing_path];
+        let mut summary = Summary::new(5);
+        let md_output =
+            generate_markdown(root, &files, &FormatOptions::default(), &mut summary).unwrap();
+
+        assert!(!md_output.contains("logo.png"));
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.lines, 1);
+        assert_eq!(summary.languages["rust"].files, 1);
+        assert_eq!(
+            summary.skipped[&SkipReason::Binary],
+            vec![PathBuf::from("logo.png")]
+        );
+        assert_eq!(
+            summary.skipped[&SkipReason::Unreadable],
+            vec![PathBuf::from("missing.rs")]
+        );
+        assert_eq!(
+            summary.manifest.files.keys().collect::<Vec<_>>(),
+            vec!["ok.rs"]
+        );
+    }
+
+    #[test]
+    fn test_generate_markdown_skips_large_file() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let small_path = root.join("small.rs");
+        fs::write(&small_path, "fn a() {}\n").unwrap();
+        let large_path = root.join("large.rs");
+        fs::write(&large_path, "fn b() {}\n".repeat(100)).unwrap();
+
+        let files = vec![small_path, large_path];
+        let options = FormatOptions {
+            max_size: Some(100),
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output = generate_markdown(root, &files, &options, &mut summary).unwrap();
+
+        assert!(md_output.contains("### `small.rs`"));
+        assert!(!md_output.contains("### `large.rs`"));
+        assert_eq!(
+            summary.skipped[&SkipReason::TooLarge],
+            vec![PathBuf::from("large.rs")]
+        );
+    }
+
+    #[test]
+    fn test_generate_markdown_outline() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let rs_path = root.join("lib.rs");
+        fs::write(&rs_path, "pub fn answer() -> u32 {\n    42\n}\n").unwrap();
+        let txt_path = root.join("notes.txt");
+        fs::write(&txt_path, "fn not_code() { kept }\n").unwrap();
+
+        let files = vec![rs_path, txt_path];
+        let options = FormatOptions {
+            outline: true,
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output = generate_markdown(root, &files, &options, &mut summary).unwrap();
+
+        assert!(md_output.contains("pub fn answer() -> u32 { … }"));
+        assert!(!md_output.contains("42"));
+        assert!(md_output.contains("fn not_code() { kept }"));
+    }
+
+    #[test]
+    fn test_generate_markdown_strips_comments() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let rs_path = root.join("lib.rs");
+        fs::write(
+            &rs_path,
+            "// License banner\n\n\n/// Docs.\nfn a() -> &'static str { \"// kept\" } // gone\n",
+        )
+        .unwrap();
+
+        let options = FormatOptions {
+            transform: TransformOptions {
+                strip_comments: true,
+                keep_doc_comments: true,
+                compact_whitespace: true,
+            },
+            ..Default::default()
+        };
+        let md_output =
+            generate_markdown(root, &[rs_path], &options, &mut Summary::default()).unwrap();
+
+        assert!(
+            md_output.contains("```rust\n/// Docs.\nfn a() -> &'static str { \"// kept\" }\n\n```")
+        );
+    }
+
+    #[test]
+    fn test_generate_markdown_grep_regions() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let path = root.join("lib.rs");
+        let lines: Vec<String> = (1..=12).map(|i| format!("// line {}", i)).collect();
+        fs::write(&path, lines.join("\n").replace("line 4", "line 4 needle")).unwrap();
+
+        let options = FormatOptions {
+            grep: Some(GrepOptions {
+                patterns: vec![regex::Regex::new("needle").unwrap()],
+                invert: false,
+                context: Some(1),
+            }),
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output = generate_markdown(root, &[path], &options, &mut summary).unwrap();
+
+        assert_eq!(
+            md_output,
+            "### `lib.rs` (lines 3-5)\n\n```rust\n// line 3\n// line 4 needle\n// line 5\n```\n\n"
+        );
+        assert_eq!(summary.lines, 3);
+    }
+
+    #[test]
+    fn test_generate_markdown_notebook() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let notebook = root.join("explore.ipynb");
+        fs::write(
+            &notebook,
+            r#"{"metadata": {}, "cells": [
+                {"cell_type": "markdown", "source": "Notes"},
+                {"cell_type": "code", "source": "x = 1", "outputs": []}
+            ]}"#,
+        )
+        .unwrap();
+        let broken = root.join("broken.ipynb");
+        fs::write(&broken, "{\"cells\": ").unwrap();
+
+        let files = vec![notebook, broken];
+        let mut summary = Summary::new(5);
+        let md_output =
+            generate_markdown(root, &files, &FormatOptions::default(), &mut summary).unwrap();
+
+        assert!(md_output.contains("### `explore.ipynb`\n\nNotes\n\n```python\nx = 1\n```\n\n"));
+        assert!(md_output.contains("### `broken.ipynb`\n\n```json\n{\"cells\": \n```"));
+    }
+
+    #[test]
+    fn test_generate_markdown_grep_keeps_rendered_files_whole() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let notebook = root.join("nb.ipynb");
+        fs::write(
+            &notebook,
+            r#"{"metadata": {}, "cells": [
+                {"cell_type": "markdown", "source": "Notes"},
+                {"cell_type": "code", "source": "x = 1\ny = needle", "outputs": []}
+            ]}"#,
+        )
+        .unwrap();
+        let readme = root.join("README.md");
+        fs::write(&readme, "# Title\n\nneedle here\n\nmore\n").unwrap();
+
+        let options = FormatOptions {
+            grep: Some(GrepOptions {
+                patterns: vec![regex::Regex::new("needle").unwrap()],
+                invert: false,
+                context: Some(0),
+            }),
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output =
+            generate_markdown(root, &[notebook, readme], &options, &mut summary).unwrap();
+
+        assert_eq!(
+            md_output,
+            "### `nb.ipynb`\n\nNotes\n\n```python\nx = 1\ny = needle\n```\n\n\
+             ### `README.md` (lines 3-3)\n\n```markdown\nneedle here\n```\n\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_markdown_samples_data_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let rows: String = (1..=100).map(|i| format!("{},{}\n", i, i * 2)).collect();
+        let big = root.join("big.csv");
+        fs::write(&big, format!("a,b\n{}", rows)).unwrap();
+        let fixture = root.join("fixture.csv");
+        fs::write(&fixture, format!("a,b\n{}", rows)).unwrap();
+
+        let files = vec![big, fixture];
+        let options = FormatOptions {
+            sample: SampleOptions {
+                rows: 1,
+                rules: vec!["fixture.csv=all".parse().unwrap()],
+            },
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output = generate_markdown(root, &files, &options, &mut summary).unwrap();
+
+        assert!(md_output.contains(
+            "### `big.csv`\n\n```csv\na,b\n1,2\n… (98 rows omitted)\n100,200\n```\n\n\
+             _100 rows, 2 columns: `a` (integer), `b` (integer)_\n\n"
+        ));
+        assert!(md_output.contains("### `fixture.csv`\n\n```csv\na,b\n1,2\n2,4\n"));
+    }
+
+    #[test]
+    fn test_generate_markdown_summarizes_large_documents() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        let spec = root.join("spec.json");
+        fs::write(
+            &spec,
+            r#"{"paths": {"/pets": {"get": {"summary": "List pets"}}}}"#,
+        )
+        .unwrap();
+        let small = root.join("small.yaml");
+        fs::write(&small, "a: 1\n").unwrap();
+
+        let files = vec![spec, small];
+        let options = FormatOptions {
+            summarize_over: Some(20),
+            ..Default::default()
+        };
+        let mut summary = Summary::new(5);
+        let md_output = generate_markdown(root, &files, &options, &mut summary).unwrap();
+
+        assert!(md_output.contains(
+            "### `spec.json` (structure summary of 55 B)\n\n```yaml\npaths: object\n  \
+             /pets: object\n    get: object\n      summary: string  # e.g. \"List pets\"\n```"
+        ));
+        assert!(md_output.contains("### `small.yaml`\n\n```yaml\na: 1\n"));
+    }
 }
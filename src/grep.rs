@@ -0,0 +1,116 @@
+use regex::Regex;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// Content filter selected with `--grep`.
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    /// A file matches if any of these matches its contents.
+    pub patterns: Vec<Regex>,
+    /// Keep the files that do not match instead.
+    pub invert: bool,
+    /// Emit only the matching regions with this many lines of context.
+    pub context: Option<usize>,
+}
+
+impl GrepOptions {
+    /// Whether `contents` matches any of the patterns.
+    pub fn is_match(&self, contents: &str) -> bool {
+        self.patterns.iter().any(|re| re.is_match(contents))
+    }
+
+    /// Keep the `files` whose contents match, or do not match when
+    /// inverted. Files that cannot be read as text never match.
+    pub fn filter(&self, files: Vec<PathBuf>) -> Vec<PathBuf> {
+        files
+            .into_iter()
+            .filter(|file| {
+                let matched = fs::read_to_string(file).is_ok_and(|c| self.is_match(&c));
+                matched != self.invert
+            })
+            .collect()
+    }
+
+    /// The 1-based line ranges of `contents` around matching lines, with
+    /// `context` lines on either side and overlapping or adjacent ranges
+    /// merged.
+    pub fn regions(&self, contents: &str, context: usize) -> Vec<RangeInclusive<usize>> {
+        let line_count = contents.lines().count();
+        let mut regions: Vec<RangeInclusive<usize>> = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            if !self.patterns.iter().any(|re| re.is_match(line)) {
+                continue;
+            }
+            let start = (i + 1).saturating_sub(context).max(1);
+            let end = (i + 1 + context).min(line_count);
+            match regions.last_mut() {
+                Some(last) if *last.end() + 1 >= start => *last = *last.start()..=end,
+                _ => regions.push(start..=end),
+            }
+        }
+        regions
+    }
+}
+
+/// The lines of `contents` in the 1-based `range`.
+pub fn region_text(contents: &str, range: &RangeInclusive<usize>) -> String {
+    contents
+        .lines()
+        .skip(range.start() - 1)
+        .take(range.end() + 1 - range.start())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn grep(patterns: &[&str], invert: bool) -> GrepOptions {
+        GrepOptions {
+            patterns: patterns.iter().map(|p| Regex::new(p).unwrap()).collect(),
+            invert,
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_files() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a.rs");
+        let b = dir.path().join("b.rs");
+        let c = dir.path().join("c.rs");
+        fs::write(&a, "fn parse_config() {}\n").unwrap();
+        fs::write(&b, "fn main() {}\n").unwrap();
+        fs::write(&c, "// TODO: handle Error\n").unwrap();
+        let files = vec![a.clone(), b.clone(), c.clone()];
+
+        assert_eq!(
+            grep(&["parse_\\w+", "Error"], false).filter(files.clone()),
+            vec![a.clone(), c]
+        );
+        assert_eq!(grep(&["parse_\\w+", "Error"], true).filter(files), vec![b]);
+    }
+
+    #[test]
+    fn test_regions_merge_context() {
+        let contents = (1..=20)
+            .map(|i| match i {
+                3 | 5 | 15 => format!("line {} match", i),
+                _ => format!("line {}", i),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let grep = grep(&["match"], false);
+
+        assert_eq!(grep.regions(&contents, 1), vec![2..=6, 14..=16]);
+        assert_eq!(grep.regions(&contents, 0), vec![3..=3, 5..=5, 15..=15]);
+        assert_eq!(grep.regions(&contents, 10), vec![1..=20]);
+        assert_eq!(
+            region_text(&contents, &(14..=16)),
+            "line 14\nline 15 match\nline 16"
+        );
+    }
+}
@@ -1,56 +1,571 @@
-use once_cell::sync::Lazy;
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobMatcher};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use once_cell::sync::{Lazy, OnceCell};
+use regex::Regex;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-/// Data describing the file extensions and filenames for a given syntax tag.
-/// For instance: tag = "rust", extensions = [".rs"]
-#[derive(Debug, Deserialize)]
+/// Data describing the file extensions, filenames, filename prefixes and
+/// glob patterns for a given syntax tag. For instance: tag = "rust",
+/// extensions = [".rs"]
+///
+/// Extensions may be compound (".d.ts", ".blade.php"); extensions and
+/// prefixes match case-insensitively, and filenames fall back to a
+/// case-insensitive match.
+#[derive(Debug, Default, Clone, Deserialize)]
 pub struct LanguageData {
     /// File extensions (e.g. ".rs", ".py") that map to a particular code block tag.
+    #[serde(default)]
     pub extensions: Vec<String>,
     /// Exact filenames (e.g. "Dockerfile", "Makefile") that map to a particular code block tag.
+    #[serde(default)]
     pub filenames: Vec<String>,
+    /// Filename prefixes (e.g. "Dockerfile.") for variants such as
+    /// `Dockerfile.prod`.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Glob patterns (e.g. "**/templates/*.html") matched against the whole
+    /// path.
+    #[serde(default)]
+    pub globs: Vec<String>,
 }
 
 /// A compiled JSON file mapping code block tags to their corresponding
 /// [`LanguageData`] (extensions & filenames).
 static LANGUAGES_JSON: &str = include_str!("../assets/languages.json");
 
-/// A static map from extension/filename -> code block tag,
-/// used by [`detect_language`] to figure out how to fence code blocks.
-static EXT_TO_TAG: Lazy<HashMap<String, String>> = Lazy::new(|| {
-    let parsed: HashMap<String, LanguageData> =
-        serde_json::from_str(LANGUAGES_JSON).expect("invalid languages.json");
+/// What a [`Mapping`] matches on, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MappingKind {
+    Glob,
+    Filename,
+    Extension,
+    Prefix,
+}
+
+impl std::fmt::Display for MappingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(match self {
+            MappingKind::Glob => "glob",
+            MappingKind::Filename => "filename",
+            MappingKind::Extension => "extension",
+            MappingKind::Prefix => "prefix",
+        })
+    }
+}
+
+/// One entry of the effective mapping table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub kind: MappingKind,
+    /// Lowercase extension without the leading dot (possibly compound, like
+    /// `d.ts`), filename, lowercase filename prefix or glob pattern.
+    pub pattern: String,
+    pub tag: String,
+    /// Whether the entry comes from a user mappings file.
+    pub user: bool,
+}
+
+/// The merged built-in and user mappings.
+pub struct Mappings {
+    /// Effective entries: globs in the order they are tried, then the rest
+    /// sorted by kind and pattern.
+    table: Vec<Mapping>,
+    /// Glob patterns, tried first; user globs before built-in ones.
+    globs: Vec<(GlobMatcher, String)>,
+    /// Exact filename -> code block tag.
+    filenames: HashMap<String, String>,
+    /// Lowercase filename -> code block tag, for case-insensitive matches.
+    filenames_lower: HashMap<String, String>,
+    /// Lowercase extension -> code block tag.
+    extensions: HashMap<String, String>,
+    /// Lowercase filename prefix -> code block tag.
+    prefixes: Vec<(String, String)>,
+}
+
+impl Mappings {
+    /// Merge `user` mappings over the built-in `languages.json` contents.
+    ///
+    /// A user entry replaces the built-in entry for the same extension,
+    /// filename or prefix. When two languages of the same source claim one
+    /// key, the tag that sorts first wins, so the result never depends on
+    /// map iteration order.
+    pub fn new(builtin: &str, user: Vec<HashMap<String, LanguageData>>) -> Result<Self> {
+        let builtin: HashMap<String, LanguageData> = serde_json::from_str(builtin)?;
+
+        let mut entries: BTreeMap<(MappingKind, String), Mapping> = BTreeMap::new();
+        let mut globs: Vec<Mapping> = Vec::new();
+        let sources = std::iter::once((builtin, false)).chain(user.into_iter().map(|u| (u, true)));
+        for (languages, is_user) in sources {
+            let languages: BTreeMap<String, LanguageData> = languages.into_iter().collect();
+            for (tag, data) in languages {
+                let keys = data
+                    .extensions
+                    .into_iter()
+                    .map(|ext| {
+                        let ext = ext.trim_start_matches('.').to_lowercase();
+                        (MappingKind::Extension, ext)
+                    })
+                    .chain(
+                        data.filenames
+                            .into_iter()
+                            .map(|n| (MappingKind::Filename, n)),
+                    )
+                    .chain(
+                        data.prefixes
+                            .into_iter()
+                            .map(|p| (MappingKind::Prefix, p.to_lowercase())),
+                    );
+                for key in keys {
+                    let claimed_by_source = entries.get(&key).is_some_and(|m| m.user == is_user);
+                    if claimed_by_source {
+                        continue;
+                    }
+                    let mapping = Mapping {
+                        kind: key.0,
+                        pattern: key.1.clone(),
+                        tag: tag.clone(),
+                        user: is_user,
+                    };
+                    entries.insert(key, mapping);
+                }
+                globs.extend(data.globs.into_iter().map(|pattern| Mapping {
+                    kind: MappingKind::Glob,
+                    pattern,
+                    tag: tag.clone(),
+                    user: is_user,
+                }));
+            }
+        }
+        // User globs are tried before built-in ones; the sort is stable.
+        globs.sort_by_key(|m| !m.user);
+
+        let mut mappings = Mappings {
+            table: Vec::new(),
+            globs: Vec::new(),
+            filenames: HashMap::new(),
+            filenames_lower: HashMap::new(),
+            extensions: HashMap::new(),
+            prefixes: Vec::new(),
+        };
+        for m in &globs {
+            let glob = Glob::new(&m.pattern)
+                .map_err(|e| anyhow!("invalid glob `{}`: {}", m.pattern, e))?;
+            mappings.globs.push((glob.compile_matcher(), m.tag.clone()));
+        }
+        for m in entries.values() {
+            let (pattern, tag) = (m.pattern.clone(), m.tag.clone());
+            match m.kind {
+                MappingKind::Filename => {
+                    mappings
+                        .filenames_lower
+                        .entry(pattern.to_lowercase())
+                        .or_insert_with(|| tag.clone());
+                    mappings.filenames.insert(pattern, tag);
+                }
+                MappingKind::Extension => {
+                    mappings.extensions.insert(pattern, tag);
+                }
+                MappingKind::Prefix => mappings.prefixes.push((pattern, tag)),
+                MappingKind::Glob => {}
+            }
+        }
+        mappings.table = globs.into_iter().chain(entries.into_values()).collect();
+        Ok(mappings)
+    }
 
-    let mut map = HashMap::new();
+    /// The effective mapping table.
+    pub fn table(&self) -> &[Mapping] {
+        &self.table
+    }
 
-    for (tag, data) in parsed {
-        for ext in data.extensions {
-            let ext_str = ext.trim_start_matches('.').to_string();
-            map.insert(ext_str, tag.clone());
+    /// The code block tag for `path`, see [`detect_language`].
+    pub fn detect(&self, path: &Path) -> &str {
+        if let Some((_, tag)) = self.globs.iter().find(|(glob, _)| glob.is_match(path)) {
+            return tag;
         }
-        for name in data.filenames {
-            map.insert(name, tag.clone());
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            return "text";
+        };
+        if let Some(tag) = self.filenames.get(name) {
+            return tag;
         }
+        let lower = name.to_lowercase();
+        if let Some(tag) = self.filenames_lower.get(&lower) {
+            return tag;
+        }
+
+        // Every suffix after a dot, longest first: `d.ts`, then `ts`. A
+        // leading dot starts a hidden file's name, not an extension.
+        let compound = lower
+            .char_indices()
+            .filter(|&(i, c)| c == '.' && i > 0)
+            .find_map(|(i, _)| self.extensions.get(&lower[i + 1..]));
+        if let Some(tag) = compound {
+            return tag;
+        }
+
+        self.prefixes
+            .iter()
+            .filter(|(prefix, _)| lower.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or("text", |(_, tag)| tag)
     }
+}
 
-    map
+/// User mappings registered by [`load_user_languages`] before first use.
+static USER_LANGUAGES: OnceCell<Vec<HashMap<String, LanguageData>>> = OnceCell::new();
+
+/// The effective mappings: [`LANGUAGES_JSON`] merged with the user
+/// mappings, used by [`detect_language`] to figure out how to fence code
+/// blocks.
+static MAPPINGS: Lazy<Mappings> = Lazy::new(|| {
+    let user = USER_LANGUAGES.get().cloned().unwrap_or_default();
+    Mappings::new(LANGUAGES_JSON, user).expect("invalid languages.json")
 });
 
-/// Detects the code block tag for the given `path` based on its file extension
-/// or full filename, using the mapping from [`LANGUAGES_JSON`].
+/// Name of the user mappings file inside the configuration directory.
+const USER_LANGUAGES_FILE: &str = "languages.json";
+
+/// The default user mappings file:
+/// `$XDG_CONFIG_HOME/copycat/languages.json`, or
+/// `~/.config/copycat/languages.json`.
+pub fn user_languages_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("copycat").join(USER_LANGUAGES_FILE))
+}
+
+/// Read user mappings in the `languages.json` format from the default user
+/// file, if it exists, and then from `extra`, and merge them over the
+/// built-in mappings. Must be called before the first detection.
+pub fn load_user_languages(extra: Option<&Path>) -> Result<()> {
+    let mut files: Vec<PathBuf> = user_languages_path()
+        .filter(|p| p.is_file())
+        .into_iter()
+        .collect();
+    files.extend(extra.map(Path::to_path_buf));
+
+    let mut user = Vec::new();
+    for file in files {
+        let contents = fs::read_to_string(&file)
+            .map_err(|e| anyhow!("failed to read {}: {}", file.display(), e))?;
+        let languages: HashMap<String, LanguageData> = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("invalid language mappings in {}: {}", file.display(), e))?;
+        user.push(languages);
+    }
+
+    // Validate now so a bad glob is reported instead of panicking later.
+    Mappings::new(LANGUAGES_JSON, user.clone())?;
+    USER_LANGUAGES
+        .set(user)
+        .map_err(|_| anyhow!("language mappings were already loaded"))
+}
+
+/// The effective mapping table, for the `languages` subcommand.
+pub fn mapping_table() -> &'static [Mapping] {
+    MAPPINGS.table()
+}
+
+/// Detects the code block tag for the given `path`, using the mapping from
+/// [`LANGUAGES_JSON`] merged with any user mappings.
 ///
-/// If no known mapping is found, it returns `"text"` as a fallback.
+/// Glob patterns are tried first, then the exact and the case-insensitive
+/// filename, then the longest matching (possibly compound) extension and
+/// finally the longest filename prefix. If no known mapping is found, it
+/// returns `"text"` as a fallback.
 pub fn detect_language(path: &Path) -> &str {
-    match (
-        path.extension().and_then(|s| s.to_str()),
-        path.file_name().and_then(|s| s.to_str()),
-    ) {
-        (Some(ext), _) if EXT_TO_TAG.contains_key(ext) => EXT_TO_TAG.get(ext).unwrap(),
-        (_, Some(name)) if EXT_TO_TAG.contains_key(name) => EXT_TO_TAG.get(name).unwrap(),
-        _ => "text",
+    MAPPINGS.detect(path)
+}
+
+/// Alternative names used by shebangs, modelines and `.gitattributes` for
+/// code block tags.
+const ALIASES: &[(&str, &str)] = &[
+    ("sh", "shell"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("ksh", "shell"),
+    ("dash", "shell"),
+    ("fish", "shell"),
+    ("shell-script", "shell"),
+    ("shellscript", "shell"),
+    ("c++", "cpp"),
+    ("cxx", "cpp"),
+    ("c#", "csharp"),
+    ("cs", "csharp"),
+    ("js", "javascript"),
+    ("node", "javascript"),
+    ("nodejs", "javascript"),
+    ("deno", "javascript"),
+    ("bun", "javascript"),
+    ("ts", "typescript"),
+    ("ts-node", "typescript"),
+    ("py", "python"),
+    ("rb", "ruby"),
+    ("make", "makefile"),
+    ("gnumakefile", "makefile"),
+    ("objective-c", "objc"),
+    ("objective-c++", "objc++"),
+    ("md", "markdown"),
+    ("yml", "yaml"),
+    ("rscript", "r"),
+    ("pwsh", "powershell"),
+    ("ps1", "powershell"),
+    ("golang", "go"),
+];
+
+/// Map a language name from a shebang, modeline or `.gitattributes` to a
+/// code block tag, e.g. `Shell` or `bash` to `shell` and `C++` to `cpp`.
+pub fn normalize_name(name: &str) -> String {
+    let name = name.trim().to_lowercase().replace(' ', "-");
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, tag)| tag.to_string())
+}
+
+/// Vim modelines such as `vim: set ft=ruby:` or `vi: filetype=python`.
+static VIM_MODELINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|\s)(?:vi|vim|ex):.*?\b(?:ft|filetype|syntax)=([\w+#-]+)").unwrap()
+});
+
+/// Emacs file variable lines such as `-*- mode: python -*-` or `-*- ruby -*-`.
+static EMACS_MODELINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"-\*-(.*?)-\*-").unwrap());
+
+/// Number of lines at the start and end of a file searched for modelines,
+/// as in Vim.
+const MODELINE_LINES: usize = 5;
+
+/// Detects the code block tag of `path` using its `contents` as well as its
+/// name.
+///
+/// Sources are checked in this order, the first match winning:
+///
+/// 1. a `linguist-language` attribute from `.gitattributes` files between
+///    the file and the repository root,
+/// 2. a Vim or Emacs modeline,
+/// 3. the extension or filename, see [`detect_language`],
+/// 4. the interpreter named by a `#!` shebang.
+///
+/// Falls back to `"text"`.
+pub fn detect_language_of(path: &Path, contents: &str) -> String {
+    if let Some(language) = gitattributes_language(path) {
+        return language;
+    }
+    if let Some(language) = modeline_language(contents) {
+        return language;
+    }
+    match detect_language(path) {
+        "text" => shebang_language(contents).unwrap_or_else(|| "text".to_string()),
+        language => language.to_string(),
+    }
+}
+
+/// The language named by a Vim or Emacs modeline near the start or end of
+/// `contents`.
+fn modeline_language(contents: &str) -> Option<String> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let head = lines.iter().take(MODELINE_LINES);
+    let tail = lines
+        .iter()
+        .skip(MODELINE_LINES.max(lines.len().saturating_sub(MODELINE_LINES)));
+
+    // Emacs only reads the first line, or the second after a shebang.
+    let emacs = lines
+        .iter()
+        .take(if contents.starts_with("#!") { 2 } else { 1 })
+        .find_map(|line| EMACS_MODELINE.captures(line))
+        .and_then(|caps| {
+            let vars = caps[1].trim();
+            if !vars.contains(':') {
+                return Some(vars.to_string());
+            }
+            vars.split(';').find_map(|var| {
+                let (key, value) = var.split_once(':')?;
+                (key.trim().eq_ignore_ascii_case("mode")).then(|| value.trim().to_string())
+            })
+        });
+    if let Some(mode) = emacs {
+        return Some(normalize_name(&mode));
+    }
+
+    head.chain(tail)
+        .find_map(|line| VIM_MODELINE.captures(line))
+        .map(|caps| normalize_name(&caps[1]))
+}
+
+/// The language of the interpreter named by a `#!` shebang, e.g.
+/// `#!/usr/bin/env bash` or `#!/usr/bin/python3.11`.
+fn shebang_language(contents: &str) -> Option<String> {
+    let line = contents.lines().next()?.strip_prefix("#!")?;
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip `env` options such as `-S`.
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    // `python3.11` and `ruby2` name the same language as `python` and `ruby`.
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    let tag = normalize_name(name);
+    LANGUAGE_TAGS.contains(tag.as_str()).then_some(tag)
+}
+
+/// All code block tags known from [`LANGUAGES_JSON`].
+static LANGUAGE_TAGS: Lazy<HashSet<String>> =
+    Lazy::new(|| MAPPINGS.table().iter().map(|m| m.tag.clone()).collect());
+
+/// `linguist-language` rules of one `.gitattributes` file, in file order.
+type AttributeRules = Vec<(Gitignore, String)>;
+
+/// Parsed `.gitattributes` files keyed by their directory.
+static GITATTRIBUTES: Lazy<Mutex<HashMap<PathBuf, Arc<AttributeRules>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The `linguist-language` attribute set for `path` by `.gitattributes`
+/// files in its directory and its ancestors up to the repository root.
+///
+/// Deeper files take precedence over shallower ones and later lines over
+/// earlier ones, as in Git.
+fn gitattributes_language(path: &Path) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    let mut dirs = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        dirs.push(dir);
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    let mut language = None;
+    for dir in dirs.into_iter().rev() {
+        for (matcher, value) in attribute_rules(dir).iter() {
+            if matcher.matched(&path, false).is_ignore() {
+                language = Some(value.clone());
+            }
+        }
     }
+    language.map(|name| normalize_name(&name))
+}
+
+/// The parsed `linguist-language` rules of `dir/.gitattributes`, cached.
+fn attribute_rules(dir: &Path) -> Arc<AttributeRules> {
+    let mut cache = GITATTRIBUTES.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| Arc::new(parse_gitattributes(dir)))
+        .clone()
+}
+
+fn parse_gitattributes(dir: &Path) -> AttributeRules {
+    let Ok(contents) = fs::read_to_string(dir.join(".gitattributes")) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next()?;
+            let value = fields.find_map(|attr| attr.strip_prefix("linguist-language="))?;
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add_line(None, pattern).ok()?;
+            Some((builder.build().ok()?, value.to_string()))
+        })
+        .collect()
+}
+
+/// Collection filter by detected language, selected with `--lang` and
+/// `--exclude-lang`.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageFilter {
+    /// Keep only files detected as one of these code block tags, if any.
+    pub include: HashSet<String>,
+    /// Drop files detected as one of these code block tags.
+    pub exclude: HashSet<String>,
+}
+
+impl LanguageFilter {
+    /// Build a filter from language names, which may be code block tags or
+    /// aliases such as `py` or `bash`. Unknown names are an error.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let tags = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| {
+                    let tag = normalize_name(name);
+                    if tag == "text" || LANGUAGE_TAGS.contains(&tag) {
+                        Ok(tag)
+                    } else {
+                        Err(anyhow!(
+                            "unknown language `{}`, see `copycat languages`",
+                            name
+                        ))
+                    }
+                })
+                .collect::<Result<HashSet<String>>>()
+        };
+        Ok(LanguageFilter {
+            include: tags(include)?,
+            exclude: tags(exclude)?,
+        })
+    }
+
+    /// Whether files detected as `tag` are kept.
+    pub fn allows(&self, tag: &str) -> bool {
+        (self.include.is_empty() || self.include.contains(tag)) && !self.exclude.contains(tag)
+    }
+
+    /// Keep the `files` whose language, as detected by
+    /// [`detect_language_of`], is allowed. Only the start and end of each
+    /// file are read; unreadable files are detected by name alone.
+    pub fn filter(&self, files: Vec<PathBuf>) -> Vec<PathBuf> {
+        self.split(files).0
+    }
+
+    /// Like [`LanguageFilter::filter`], but also return the dropped files
+    /// together with the language they were detected as.
+    pub fn split(&self, files: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<(PathBuf, String)>) {
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+        for file in files {
+            let contents = detection_sample(&file).unwrap_or_default();
+            let tag = detect_language_of(&file, &contents);
+            if self.allows(&tag) {
+                kept.push(file);
+            } else {
+                dropped.push((file, tag));
+            }
+        }
+        (kept, dropped)
+    }
+}
+
+/// Bytes read from each end of a file to detect its language.
+const SAMPLE_BYTES: u64 = 4096;
+
+/// The parts of the file at `path` that [`detect_language_of`] looks at:
+/// the whole file when it is small, otherwise its first and last
+/// [`SAMPLE_BYTES`], which hold any shebang and modelines.
+fn detection_sample(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut bytes = Vec::new();
+    if len <= 2 * SAMPLE_BYTES {
+        file.read_to_end(&mut bytes)?;
+    } else {
+        (&mut file).take(SAMPLE_BYTES).read_to_end(&mut bytes)?;
+        bytes.push(b'\n');
+        file.seek(SeekFrom::End(-(SAMPLE_BYTES as i64)))?;
+        file.read_to_end(&mut bytes)?;
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
 }
 
 #[cfg(test)]
@@ -97,4 +612,276 @@ mod tests {
             "unknown extension should fall back to `text`"
         );
     }
+
+    #[test]
+    fn test_detect_shebang() {
+        let path = Path::new("bin/deploy");
+        assert_eq!(
+            detect_language_of(path, "#!/usr/bin/env bash\nset -e\n"),
+            "shell"
+        );
+        assert_eq!(
+            detect_language_of(path, "#!/usr/bin/python3.11\nprint()\n"),
+            "python"
+        );
+        assert_eq!(
+            detect_language_of(path, "#!/usr/bin/env -S node --harmony\n"),
+            "javascript"
+        );
+        assert_eq!(detect_language_of(path, "#!/opt/unknown-tool\n"), "text");
+        assert_eq!(detect_language_of(path, "plain text\n"), "text");
+    }
+
+    #[test]
+    fn test_detect_modelines() {
+        let path = Path::new("script.txt");
+        assert_eq!(
+            detect_language_of(path, "# vim: set ft=ruby:\nputs 1\n"),
+            "ruby"
+        );
+        assert_eq!(
+            detect_language_of(path, "line\n\n\n\n\n\n\n// vim: filetype=cpp\n"),
+            "cpp"
+        );
+        assert_eq!(
+            detect_language_of(path, "# -*- mode: python; coding: utf-8 -*-\n"),
+            "python"
+        );
+        assert_eq!(
+            detect_language_of(path, "#!/bin/sh\n# -*- perl -*-\n"),
+            "perl"
+        );
+        // Modelines in the middle of a long file are ignored.
+        let middle = format!("{}# vim: ft=ruby\n{}", "x\n".repeat(6), "x\n".repeat(6));
+        assert_eq!(detect_language_of(Path::new("notes"), &middle), "text");
+    }
+
+    #[test]
+    fn test_detect_gitattributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("scripts/legacy")).unwrap();
+        fs::write(
+            root.join(".gitattributes"),
+            "# overrides\n*.inc linguist-language=PHP\nscripts/* linguist-language=Shell\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("scripts/legacy/.gitattributes"),
+            "run linguist-language=Perl\n",
+        )
+        .unwrap();
+
+        assert_eq!(detect_language_of(&root.join("lib/db.inc"), ""), "php");
+        assert_eq!(
+            detect_language_of(&root.join("scripts/build"), "#!/usr/bin/env python\n"),
+            "shell"
+        );
+        assert_eq!(
+            detect_language_of(&root.join("scripts/legacy/run"), "# vim: ft=ruby\n"),
+            "perl"
+        );
+    }
+
+    #[test]
+    fn test_detection_order() {
+        // A modeline overrides the extension ...
+        assert_eq!(
+            detect_language_of(Path::new("config.js"), "// vim: ft=typescript\n"),
+            "typescript"
+        );
+        // ... and the extension overrides the shebang.
+        assert_eq!(
+            detect_language_of(Path::new("tool.py"), "#!/bin/bash\n"),
+            "python"
+        );
+    }
+
+    fn user_mappings(json: &str) -> Mappings {
+        let user: HashMap<String, LanguageData> = serde_json::from_str(json).unwrap();
+        Mappings::new(LANGUAGES_JSON, vec![user]).unwrap()
+    }
+
+    #[test]
+    fn test_user_mappings_extend_and_override() {
+        let mappings = user_mappings(
+            r#"{
+                "astro": { "extensions": [".astro"] },
+                "php": { "extensions": [".inc", ".rs"], "filenames": ["Phpfile"] }
+            }"#,
+        );
+
+        assert_eq!(mappings.detect(Path::new("src/page.astro")), "astro");
+        assert_eq!(mappings.detect(Path::new("lib/db.inc")), "php");
+        assert_eq!(mappings.detect(Path::new("Phpfile")), "php");
+        // User entries replace built-in ones.
+        assert_eq!(mappings.detect(Path::new("main.rs")), "php");
+        // Everything else is untouched.
+        assert_eq!(mappings.detect(Path::new("app.py")), "python");
+
+        let inc = mappings
+            .table()
+            .iter()
+            .find(|m| m.pattern == "inc")
+            .unwrap();
+        assert_eq!(inc.kind, MappingKind::Extension);
+        assert!(inc.user);
+    }
+
+    #[test]
+    fn test_user_glob_mappings() {
+        let mappings = user_mappings(
+            r#"{
+                "jinja": { "globs": ["**/templates/*.html"] },
+                "json": { "globs": ["**/.eslintrc"] }
+            }"#,
+        );
+
+        assert_eq!(
+            mappings.detect(Path::new("app/templates/index.html")),
+            "jinja"
+        );
+        assert_eq!(mappings.detect(Path::new("app/static/index.html")), "html");
+        assert_eq!(mappings.detect(Path::new("web/.eslintrc")), "json");
+        assert_eq!(mappings.table()[0].kind, MappingKind::Glob);
+    }
+
+    #[test]
+    fn test_invalid_user_glob() {
+        let user: HashMap<String, LanguageData> =
+            serde_json::from_str(r#"{ "x": { "globs": ["src/[a"] } }"#).unwrap();
+        assert!(Mappings::new(LANGUAGES_JSON, vec![user]).is_err());
+    }
+
+    #[test]
+    fn test_compound_and_case_insensitive_extensions() {
+        let mappings = user_mappings("{}");
+
+        assert_eq!(mappings.detect(Path::new("views/user.blade.php")), "blade");
+        assert_eq!(mappings.detect(Path::new("views/index.html.erb")), "erb");
+        assert_eq!(mappings.detect(Path::new("views/index.erb")), "erb");
+        assert_eq!(mappings.detect(Path::new("src/index.php")), "php");
+        assert_eq!(mappings.detect(Path::new("src/MAIN.RS")), "rust");
+        assert_eq!(mappings.detect(Path::new("lib/config.test.Py")), "python");
+        // A hidden file's leading dot does not start an extension.
+        assert_eq!(mappings.detect(Path::new(".rs")), "text");
+    }
+
+    #[test]
+    fn test_filename_case_and_prefixes() {
+        let mappings = user_mappings("{}");
+
+        assert_eq!(mappings.detect(Path::new("DOCKERFILE")), "dockerfile");
+        assert_eq!(mappings.detect(Path::new("Dockerfile.prod")), "dockerfile");
+        assert_eq!(mappings.detect(Path::new("dockerfile.dev")), "dockerfile");
+        assert_eq!(mappings.detect(Path::new("Makefile.am")), "makefile");
+        // An extension beats a prefix.
+        assert_eq!(mappings.detect(Path::new("Makefile.py")), "python");
+    }
+
+    #[test]
+    fn test_longest_extension_and_prefix_win() {
+        let mappings = user_mappings(
+            r#"{
+                "dts": { "extensions": [".d.ts"] },
+                "env": { "prefixes": [".env"] },
+                "envlocal": { "prefixes": [".env.local"] }
+            }"#,
+        );
+
+        assert_eq!(mappings.detect(Path::new("types/index.d.ts")), "dts");
+        assert_eq!(mappings.detect(Path::new("src/index.ts")), "typescript");
+        assert_eq!(mappings.detect(Path::new("src/App.tsx")), "tsx");
+        assert_eq!(mappings.detect(Path::new(".env.production")), "env");
+        assert_eq!(mappings.detect(Path::new(".env.local.backup")), "envlocal");
+    }
+
+    #[test]
+    fn test_conflicting_claims_are_deterministic() {
+        let json = r#"{
+            "zeta": { "extensions": [".conf", ".Zz"] },
+            "alpha": { "extensions": ["conf"], "filenames": ["Buildfile"] },
+            "beta": { "filenames": ["Buildfile"], "extensions": [".ZZ"] }
+        }"#;
+        for _ in 0..8 {
+            let mappings = user_mappings(json);
+            assert_eq!(mappings.detect(Path::new("app.conf")), "alpha");
+            assert_eq!(mappings.detect(Path::new("x.zz")), "beta");
+            assert_eq!(mappings.detect(Path::new("Buildfile")), "alpha");
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_language_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<PathBuf> = [
+            ("main.rs", "fn main() {}\n"),
+            ("Cargo.toml", "[package]\n"),
+            ("Dockerfile", "FROM rust\n"),
+            ("build", "#!/usr/bin/env python3\nprint()\n"),
+            ("README.md", "# Title\n"),
+            ("notes", "plain\n"),
+        ]
+        .iter()
+        .map(|(name, contents)| {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        })
+        .collect();
+        let kept = |filter: LanguageFilter| -> Vec<String> {
+            filter
+                .filter(files.clone())
+                .iter()
+                .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let filter = LanguageFilter::new(&names(&["rust", "TOML"]), &[]).unwrap();
+        assert_eq!(kept(filter), vec!["main.rs", "Cargo.toml"]);
+
+        let filter = LanguageFilter::new(&names(&["py", "dockerfile"]), &[]).unwrap();
+        assert_eq!(kept(filter), vec!["Dockerfile", "build"]);
+
+        let filter = LanguageFilter::new(&[], &names(&["md", "text", "rust"])).unwrap();
+        assert_eq!(kept(filter), vec!["Cargo.toml", "Dockerfile", "build"]);
+
+        let filter = LanguageFilter::new(&names(&["rust", "toml"]), &names(&["toml"])).unwrap();
+        assert_eq!(kept(filter), vec!["main.rs"]);
+
+        let filter = LanguageFilter::new(&[], &names(&["toml", "dockerfile"])).unwrap();
+        let (_, dropped) = filter.split(files.clone());
+        assert_eq!(
+            dropped,
+            vec![
+                (files[1].clone(), "toml".to_string()),
+                (files[2].clone(), "dockerfile".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detection_sample_reads_both_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script");
+        let body = "x = 1\n".repeat(10_000);
+        fs::write(&path, format!("#!/bin/sh\n{}# vim: ft=python\n", body)).unwrap();
+
+        let sample = detection_sample(&path).unwrap();
+        assert!(sample.len() <= 2 * SAMPLE_BYTES as usize + 1);
+        assert!(sample.starts_with("#!/bin/sh\n"));
+        assert!(sample.ends_with("# vim: ft=python\n"));
+        assert_eq!(detect_language_of(&path, &sample), "python");
+    }
+
+    #[test]
+    fn test_language_filter_unknown_name() {
+        let err = LanguageFilter::new(&names(&["rust", "klingon"]), &[]).unwrap_err();
+        assert!(err.to_string().contains("klingon"));
+    }
 }
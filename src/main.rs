@@ -1,30 +1,69 @@
+mod archive;
 mod files;
+mod follow;
 mod formatter;
+mod grep;
 mod language;
+mod manifest;
+mod notebook;
+mod outline;
+mod rank;
+mod revision;
+mod sample;
+mod schema;
+mod select;
+mod stats;
+mod transform;
 mod tree;
+mod watch;
 
 use anyhow::Result;
-use clap::{ArgAction, Parser};
+use archive::Archive;
+use clap::{ArgAction, Parser, Subcommand};
 use copypasta::{ClipboardContext, ClipboardProvider};
+use files::{Skipped, WalkOptions};
+use follow::Follow;
+use formatter::FormatOptions;
+use grep::GrepOptions;
+use language::LanguageFilter;
+use manifest::{Manifest, ManifestStore};
+use notebook::{NotebookOptions, NotebookOutputs};
+use rank::RankOptions;
+use regex::Regex;
+use sample::{SampleOptions, SampleRule};
+use select::Selection;
+use stats::Summary;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use transform::TransformOptions;
+use tree::{TreeFormat, TreeOptions};
+use watch::Changes;
 
 /// Command-line arguments for the `copycat` application.
 #[derive(Parser, Debug)]
 #[command(
     name = "copycat",
     version,
-    about = env!("CARGO_PKG_DESCRIPTION")
+    about = env!("CARGO_PKG_DESCRIPTION"),
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Args {
-    /// Path to repository directory or a single file.
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to repository directory, a single file, or a zip, tar or
+    /// tar.gz archive.
+    #[arg(required = true)]
+    path: Option<PathBuf>,
 
     /// One or more glob patterns for excluding files (e.g. ".gitignore", "**/*.md").
     /// Can be repeated multiple times.
     #[arg(long = "exclude", short = 'e', action = ArgAction::Append)]
     excludes: Vec<String>,
 
-    /// Sort files alphabetically.
+    /// Sort files alphabetically (the tree is always sorted).
     #[arg(long = "sort", short = 's', action = ArgAction::SetTrue)]
     sort: bool,
 
@@ -32,64 +71,743 @@ struct Args {
     #[arg(long = "tree", short = 't', action = ArgAction::SetTrue)]
     tree: bool,
 
+    /// Annotate the tree with sizes, line counts, token estimates and languages.
+    #[arg(long = "annotate", short = 'a', action = ArgAction::SetTrue, requires = "tree")]
+    annotate: bool,
+
+    /// Only render the tree down to this depth (1 = top-level entries).
+    #[arg(long = "depth", requires = "tree")]
+    depth: Option<usize>,
+
+    /// Only render directories in the tree.
+    #[arg(long = "dirs-only", action = ArgAction::SetTrue, requires = "tree")]
+    dirs_only: bool,
+
+    /// Collapse tree directories containing more than this many files into
+    /// a single summary line.
+    #[arg(long = "collapse", requires = "tree")]
+    collapse: Option<usize>,
+
+    /// Include directories without any collected files in the tree.
+    #[arg(long = "empty-dirs", action = ArgAction::SetTrue, requires = "tree")]
+    empty_dirs: bool,
+
+    /// Show ignored and excluded entries in the tree as marked, collapsed nodes.
+    #[arg(long = "show-ignored", action = ArgAction::SetTrue, requires = "tree")]
+    show_ignored: bool,
+
+    /// Format of the tree output.
+    #[arg(long = "tree-format", value_enum, default_value_t = TreeFormat::Ascii, requires = "tree")]
+    tree_format: TreeFormat,
+
     /// Print to stdout instead of copying to clipboard.
     #[arg(long = "print", short = 'p', action = ArgAction::SetTrue)]
     print: bool,
+
+    /// Write to this file instead of copying to clipboard.
+    #[arg(long = "output", short = 'o', conflicts_with = "print")]
+    output: Option<PathBuf>,
+
+    /// Keep running and regenerate the output whenever collected files change.
+    #[arg(long = "watch", short = 'w', action = ArgAction::SetTrue)]
+    watch: bool,
+
+    /// Milliseconds to wait for file changes to settle in watch mode.
+    #[arg(long = "debounce", default_value_t = 500)]
+    debounce: u64,
+
+    /// Print the summary report on stderr as JSON.
+    #[arg(long = "stats-json", action = ArgAction::SetTrue)]
+    stats_json: bool,
+
+    /// Do not print the summary report, which also saves looking for the
+    /// files that were skipped.
+    #[arg(long = "no-summary", action = ArgAction::SetTrue, conflicts_with = "stats_json")]
+    no_summary: bool,
+
+    /// Number of largest files listed in the summary report.
+    #[arg(long = "top", default_value_t = 5)]
+    top: usize,
+
+    /// Skip files larger than this many bytes. Applies before data files
+    /// are sampled and JSON or YAML files are summarized.
+    #[arg(long = "max-size")]
+    max_size: Option<u64>,
+
+    /// Embed only the API surface of supported languages (Rust, Python,
+    /// TypeScript/JavaScript, Go, Java) with function bodies elided.
+    #[arg(long = "outline", action = ArgAction::SetTrue, conflicts_with = "tree")]
+    outline: bool,
+
+    /// Remove comments using each language's lexical rules; string literals
+    /// are left untouched.
+    #[arg(long = "strip-comments", action = ArgAction::SetTrue, conflicts_with = "tree")]
+    strip_comments: bool,
+
+    /// Keep documentation comments (`///`, `/** */`, ...) when stripping.
+    #[arg(long = "keep-doc-comments", action = ArgAction::SetTrue, requires = "strip_comments")]
+    keep_doc_comments: bool,
+
+    /// Strip trailing whitespace and collapse runs of blank lines.
+    #[arg(long = "compact", action = ArgAction::SetTrue, conflicts_with = "tree")]
+    compact: bool,
+
+    /// How to include the outputs of notebook code cells.
+    #[arg(long = "notebook-outputs", value_enum, default_value_t = NotebookOutputs::Truncate, conflicts_with = "tree")]
+    notebook_outputs: NotebookOutputs,
+
+    /// Lines kept per notebook cell output when truncating.
+    #[arg(
+        long = "notebook-output-lines",
+        value_name = "LINES",
+        default_value_t = 20
+    )]
+    notebook_output_lines: usize,
+
+    /// Leave notebook image outputs out instead of noting them.
+    #[arg(long = "strip-images", action = ArgAction::SetTrue, conflicts_with = "tree")]
+    strip_images: bool,
+
+    /// Rows kept at the start and at the end of CSV, TSV and JSON Lines
+    /// files, which are embedded as a sample with their row count and
+    /// column types.
+    #[arg(
+        long = "sample-rows",
+        value_name = "ROWS",
+        default_value_t = 5,
+        conflicts_with = "tree"
+    )]
+    sample_rows: usize,
+
+    /// Sample data files matching a glob with a different number of rows,
+    /// or embed them in full with `all` (e.g. "fixtures/*.csv=all"). Can be
+    /// repeated; later rules win.
+    #[arg(long = "sample", value_name = "PATTERN=ROWS", action = ArgAction::Append, conflicts_with = "tree")]
+    sample: Vec<SampleRule>,
+
+    /// Embed JSON and YAML files larger than this many bytes as an inferred
+    /// schema (keys, types, array lengths and example values).
+    #[arg(long = "summarize-over", value_name = "BYTES", conflicts_with = "tree")]
+    summarize_over: Option<u64>,
+
+    /// Only include this entry file and the local files it imports,
+    /// transitively (Rust, JS/TS and Python), in dependency order.
+    #[arg(long = "follow", value_name = "ENTRY", conflicts_with = "tree")]
+    follow: Option<PathBuf>,
+
+    /// Follow imports at most this many levels below the entry file.
+    #[arg(long = "follow-depth", requires = "follow")]
+    follow_depth: Option<usize>,
+
+    /// Only include files whose contents match this regex. Can be repeated;
+    /// a file is kept if any pattern matches.
+    #[arg(long = "grep", value_name = "REGEX", action = ArgAction::Append, conflicts_with = "tree")]
+    grep: Vec<Regex>,
+
+    /// Keep the files that do not match any --grep pattern instead.
+    #[arg(long = "grep-invert", action = ArgAction::SetTrue, requires = "grep")]
+    grep_invert: bool,
+
+    /// Embed only the matching regions with this many lines of context,
+    /// headed by their line ranges.
+    #[arg(
+        long = "grep-context",
+        value_name = "LINES",
+        requires = "grep",
+        conflicts_with_all = ["grep_invert", "outline", "strip_comments", "compact"]
+    )]
+    grep_context: Option<usize>,
+
+    /// Only include files detected as one of these languages (code block
+    /// tags or aliases, e.g. "rust,toml"), in contents and tree output.
+    #[arg(long = "lang", value_name = "LANGS", value_delimiter = ',', action = ArgAction::Append)]
+    langs: Vec<String>,
+
+    /// Leave out files detected as one of these languages (e.g.
+    /// "json,markdown").
+    #[arg(long = "exclude-lang", value_name = "LANGS", value_delimiter = ',', action = ArgAction::Append)]
+    exclude_langs: Vec<String>,
+
+    /// Rank files by relevance to this task description (offline BM25 over
+    /// paths, identifiers and comments) and include the best matches first.
+    #[arg(long = "rank", value_name = "QUERY", conflicts_with = "tree")]
+    rank: Option<String>,
+
+    /// Include at most this many ranked files.
+    #[arg(long = "rank-top", value_name = "N", requires = "rank")]
+    rank_top: Option<usize>,
+
+    /// Include the best ranked files that fit in this many estimated tokens.
+    #[arg(long = "token-budget", value_name = "TOKENS", requires = "rank")]
+    token_budget: Option<usize>,
+
+    /// Collect the files of this commit, tag or branch straight from the
+    /// git object database instead of the working tree (bare repositories
+    /// work too).
+    #[arg(long = "rev", value_name = "REV", conflicts_with = "watch")]
+    rev: Option<String>,
+
+    /// Only include files added or modified since the last copy of this
+    /// path, and list the ones deleted since.
+    #[arg(long = "since-last", action = ArgAction::SetTrue, conflicts_with = "tree")]
+    since_last: bool,
+
+    /// The input as given on the command line, when `path` points at its
+    /// unpacked files.
+    #[arg(skip)]
+    source: Option<PathBuf>,
+
+    /// Entries of the input archive that were not unpacked.
+    #[arg(skip)]
+    unpacked_skipped: Vec<Skipped>,
+
+    /// List every candidate path with the decision taken for it and why,
+    /// without copying anything.
+    #[arg(long = "dry-run", action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// Do not follow symbolic links.
+    #[arg(long = "no-follow-links", action = ArgAction::SetTrue)]
+    no_follow_links: bool,
+
+    /// Skip hidden files and directories.
+    #[arg(long = "no-hidden", action = ArgAction::SetTrue)]
+    no_hidden: bool,
+
+    /// Do not honor .gitignore, .git/info/exclude or the global gitignore.
+    #[arg(long = "no-gitignore", action = ArgAction::SetTrue)]
+    no_gitignore: bool,
+
+    /// Honor the repository's .git/info/exclude file.
+    #[arg(long = "git-exclude", action = ArgAction::SetTrue)]
+    git_exclude: bool,
+
+    /// Do not honor the global gitignore (core.excludesFile).
+    #[arg(long = "no-git-global", action = ArgAction::SetTrue)]
+    no_git_global: bool,
+
+    /// Maximum directory depth to descend into (1 = only top-level entries).
+    #[arg(long = "max-depth")]
+    max_depth: Option<usize>,
+
+    /// Do not cross file system boundaries.
+    #[arg(long = "same-file-system", action = ArgAction::SetTrue)]
+    same_file_system: bool,
+
+    /// Additional ignore file name using gitignore syntax (e.g. ".llmignore").
+    /// Can be repeated multiple times.
+    #[arg(long = "ignore-file", action = ArgAction::Append)]
+    ignore_filenames: Vec<String>,
+
+    /// Extra language mappings in the `languages.json` format (extensions,
+    /// filenames and globs per code block tag), merged over the built-in ones
+    /// and ~/.config/copycat/languages.json.
+    #[arg(long = "languages-file", value_name = "FILE", global = true)]
+    languages_file: Option<PathBuf>,
+}
+
+/// Subcommands; without one, copycat copies `path`.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the effective language mapping table.
+    Languages,
+}
+
+impl Args {
+    /// The collected path; clap requires it unless a subcommand is given.
+    fn path(&self) -> &Path {
+        self.path
+            .as_deref()
+            .expect("path is required without a subcommand")
+    }
+
+    /// Walker options selected on the command line.
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            follow_links: !self.no_follow_links,
+            skip_hidden: self.no_hidden,
+            git_ignore: !self.no_gitignore,
+            git_exclude: self.git_exclude && !self.no_gitignore,
+            git_global: !self.no_git_global && !self.no_gitignore,
+            max_depth: self.max_depth,
+            same_file_system: self.same_file_system,
+            ignore_filenames: self.ignore_filenames.clone(),
+        }
+    }
+
+    /// Exclude patterns from the command line, plus the `--output` file when
+    /// it lives inside the collected directory so it never includes itself.
+    fn excludes(&self) -> Vec<String> {
+        let mut excludes = self.excludes.clone();
+        if let Some(output) = &self.output {
+            if let (Ok(root), Ok(output)) = (
+                std::path::absolute(self.path()),
+                std::path::absolute(output),
+            ) {
+                if let Ok(rel) = output.strip_prefix(root) {
+                    excludes.push(format!("/{}", rel.display()));
+                }
+            }
+        }
+        excludes
+    }
+
+    /// The `--grep` filter, if any patterns were given.
+    fn grep(&self) -> Option<GrepOptions> {
+        (!self.grep.is_empty()).then(|| GrepOptions {
+            patterns: self.grep.clone(),
+            invert: self.grep_invert,
+            context: self.grep_context,
+        })
+    }
+
+    /// The `--lang` and `--exclude-lang` filter, if either was given.
+    fn languages(&self) -> Result<Option<LanguageFilter>> {
+        if self.langs.is_empty() && self.exclude_langs.is_empty() {
+            return Ok(None);
+        }
+        LanguageFilter::new(&self.langs, &self.exclude_langs).map(Some)
+    }
+
+    /// The language filter, `--follow` entry and `--rank` query narrowing
+    /// the collected files.
+    ///
+    /// The entry is resolved against the collected directory when it does
+    /// not exist relative to the working directory.
+    fn selection(&self) -> Result<Selection> {
+        let follow = self.follow.as_ref().map(|entry| Follow {
+            entry: if entry.exists() {
+                entry.clone()
+            } else {
+                self.path().join(entry)
+            },
+            max_depth: self.follow_depth,
+        });
+        let rank = self.rank.as_ref().map(|query| RankOptions {
+            query: query.clone(),
+            top: self.rank_top,
+            token_budget: self.token_budget,
+        });
+        let since_last = if self.since_last {
+            let flags = self.selection_flags();
+            let previous = match self.manifests()?.latest()? {
+                None => {
+                    eprintln!("no previous copy recorded, including every file");
+                    None
+                }
+                Some(previous) if previous.selection != flags => {
+                    eprintln!(
+                        "the last copy was made with other selection flags ({}), including every file",
+                        describe_flags(&previous.selection)
+                    );
+                    None
+                }
+                previous => previous,
+            };
+            Some(previous.unwrap_or_default())
+        } else {
+            None
+        };
+        Ok(Selection {
+            languages: self.languages()?,
+            follow,
+            rank,
+            since_last,
+        })
+    }
+
+    /// The flags that decide which files are copied, as recorded in the
+    /// manifest of each copy: a `--since-last` copy is only compared with a
+    /// copy made with the same ones.
+    fn selection_flags(&self) -> String {
+        let mut flags = Vec::new();
+        let mut flag = |name: &str, value: Option<String>| match value {
+            Some(value) if value.is_empty() => flags.push(name.to_string()),
+            Some(value) => flags.push(format!("{} {}", name, value)),
+            None => {}
+        };
+        let list = |values: &[String]| (!values.is_empty()).then(|| values.join(","));
+        let set = |enabled: bool| enabled.then(String::new);
+
+        flag("--exclude", list(&self.excludes));
+        flag("--lang", list(&self.langs));
+        flag("--exclude-lang", list(&self.exclude_langs));
+        flag(
+            "--follow",
+            self.follow.as_ref().map(|f| f.display().to_string()),
+        );
+        flag("--follow-depth", self.follow_depth.map(|d| d.to_string()));
+        let patterns: Vec<String> = self.grep.iter().map(|re| re.to_string()).collect();
+        flag("--grep", list(&patterns));
+        flag("--grep-invert", set(self.grep_invert));
+        flag("--rank", self.rank.clone());
+        flag("--rank-top", self.rank_top.map(|n| n.to_string()));
+        flag("--token-budget", self.token_budget.map(|n| n.to_string()));
+        flag("--max-size", self.max_size.map(|n| n.to_string()));
+        flag("--no-follow-links", set(self.no_follow_links));
+        flag("--no-hidden", set(self.no_hidden));
+        flag("--no-gitignore", set(self.no_gitignore));
+        flag("--git-exclude", set(self.git_exclude));
+        flag("--no-git-global", set(self.no_git_global));
+        flag("--max-depth", self.max_depth.map(|d| d.to_string()));
+        flag("--same-file-system", set(self.same_file_system));
+        flag("--ignore-file", list(&self.ignore_filenames));
+        flags.join(" ")
+    }
+
+    /// The manifests recorded for the input path.
+    fn manifests(&self) -> Result<ManifestStore> {
+        ManifestStore::for_root(self.source.as_deref().unwrap_or(self.path()))
+    }
 }
 
-/// Gather full Markdown of all collected files.
-fn get_contents(path: &Path, excludes: &[String], sort: bool) -> Result<String> {
-    let files = files::collect_files(path.to_path_buf(), excludes, sort)
+/// Gather full Markdown of all collected files, narrowed down by the
+/// selection and the `--grep` filter, recording what was included and
+/// skipped in `summary`. Paths left out by the walk are only looked for
+/// when the summary is printed.
+///
+/// Also returns the manifest for a later `--since-last`: the files read for
+/// the output or, with `--since-last`, all selected files before narrowing
+/// them down to the ones changed since the last copy.
+fn get_contents(
+    args: &Args,
+    walk: &WalkOptions,
+    excludes: &[String],
+    options: &FormatOptions,
+    summary: &mut Summary,
+) -> Result<(String, Manifest)> {
+    let path = args.path();
+    let files = files::collect_files(path.to_path_buf(), excludes, args.sort, walk)
         .map_err(|e| anyhow::anyhow!("failed to collect files: {}", e))?;
 
     if files.is_empty() {
         anyhow::bail!("no matching files found, nothing to copy");
     }
 
-    let markdown = formatter::generate_markdown(path, &files)
+    let mut skipped = if args.no_summary {
+        Vec::new()
+    } else {
+        files::collect_skipped(path, excludes, &files, walk)
+            .map_err(|e| anyhow::anyhow!("failed to collect skipped files: {}", e))?
+    };
+
+    let selection = args.selection()?;
+    let selected = select::select_files(path, files, &selection, options.grep.as_ref())?;
+    skipped.extend(selected.skipped.iter().cloned());
+    for skip in skipped {
+        let rel_path = skip.path.strip_prefix(path).unwrap_or(&skip.path);
+        summary.record_skip(rel_path, skip.reason);
+    }
+    if let Some(message) = selected.nothing_left() {
+        anyhow::bail!(message);
+    }
+
+    let mut markdown = formatter::generate_markdown(path, &selected.files, options, summary)
         .map_err(|e| anyhow::anyhow!("failed to read files: {}", e))?;
-    Ok(markdown)
+    if !selected.deleted.is_empty() {
+        markdown.push_str("### Deleted since the last copy\n\n");
+        for rel in &selected.deleted {
+            markdown.push_str(&format!("- `{}`\n", rel));
+        }
+        markdown.push('\n');
+    }
+    let manifest = selected
+        .manifest
+        .unwrap_or_else(|| std::mem::take(&mut summary.manifest));
+    Ok((markdown, manifest))
+}
+
+/// List every candidate path under `path` with the decision taken for it:
+/// included, or skipped together with the rule, filter or check
+/// responsible. Files are narrowed down by `selection` and `grep` the same
+/// way as for [`get_contents`]; `unpacked` lists the archive entries that
+/// were never unpacked.
+fn get_dry_run(
+    path: &Path,
+    excludes: &[String],
+    walk: &WalkOptions,
+    selection: &Selection,
+    grep: Option<&GrepOptions>,
+    unpacked: &[Skipped],
+    max_size: Option<u64>,
+) -> Result<String> {
+    let files = files::collect_files(path.to_path_buf(), excludes, true, walk)
+        .map_err(|e| anyhow::anyhow!("failed to collect files: {}", e))?;
+    let mut skipped = files::collect_skipped(path, excludes, &files, walk)
+        .map_err(|e| anyhow::anyhow!("failed to collect skipped files: {}", e))?;
+    skipped.extend(unpacked.iter().cloned());
+    let selected = select::select_files(path, files, selection, grep)?;
+    skipped.extend(selected.skipped);
+
+    let mut included = Vec::new();
+    for file in selected.files {
+        match files::read_text(&file, max_size) {
+            Ok(_) => included.push(file),
+            Err(skip) => skipped.push(skip),
+        }
+    }
+
+    let mut lines: Vec<(PathBuf, String)> = included
+        .into_iter()
+        .map(|file| (file, "included".to_string()))
+        .collect();
+    for skip in skipped {
+        let decision = match skip.detail {
+            Some(detail) => format!("{} ({})", skip.reason, detail),
+            None => skip.reason.to_string(),
+        };
+        lines.push((skip.path, decision));
+    }
+    lines.sort();
+
+    let mut output = String::new();
+    for (file, decision) in lines {
+        let rel_path = file.strip_prefix(path).unwrap_or(&file);
+        let rel_path = if rel_path.as_os_str().is_empty() {
+            &file
+        } else {
+            rel_path
+        };
+        output.push_str(&format!("{}: {}\n", rel_path.display(), decision));
+    }
+    Ok(output)
+}
+
+/// `flags` from [`Args::selection_flags`] for a message.
+fn describe_flags(flags: &str) -> &str {
+    if flags.is_empty() {
+        "none"
+    } else {
+        flags
+    }
+}
+
+/// Print `summary` on stderr, either as text or as JSON.
+fn print_summary(summary: &Summary, json: bool) -> Result<()> {
+    if json {
+        eprintln!("{}", summary.to_json()?);
+    } else {
+        eprint!("{}", summary);
+    }
+    Ok(())
 }
 
-/// Build an ASCII tree of all collected files & directories.
-fn get_tree(path: &Path, excludes: &[String], sort: bool) -> Result<String> {
-    let tree = tree::collect_tree(path.to_path_buf(), excludes, sort)
+/// Build a tree of all collected files & directories.
+fn get_tree(
+    path: &Path,
+    excludes: &[String],
+    walk: &WalkOptions,
+    options: &TreeOptions,
+) -> Result<String> {
+    let tree = tree::collect_tree(path.to_path_buf(), excludes, walk, options)
         .map_err(|e| anyhow::anyhow!("failed to build tree: {}", e))?;
     Ok(tree)
 }
 
+/// Deliver `output` to stdout, the `--output` file or the clipboard.
+fn emit(args: &Args, output: String) -> Result<()> {
+    if args.print {
+        println!("{}", output);
+    } else if let Some(file) = &args.output {
+        fs::write(file, output)
+            .map_err(|e| anyhow::anyhow!("failed to write {}: {}", file.display(), e))?;
+    } else {
+        let mut ctx = ClipboardContext::new()
+            .map_err(|e| anyhow::anyhow!("failed to create clipboard context: {}", e))?;
+        ctx.set_contents(output)
+            .map_err(|e| anyhow::anyhow!("failed to set clipboard contents: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Generate the tree or contents output, deliver it and report the summary.
+fn run(args: &Args, walk: &WalkOptions, excludes: &[String]) -> Result<()> {
+    let options = FormatOptions {
+        max_size: args.max_size,
+        outline: args.outline,
+        transform: TransformOptions {
+            strip_comments: args.strip_comments,
+            keep_doc_comments: args.keep_doc_comments,
+            compact_whitespace: args.compact,
+        },
+        grep: args.grep(),
+        notebook: NotebookOptions {
+            outputs: args.notebook_outputs,
+            output_lines: args.notebook_output_lines,
+            strip_images: args.strip_images,
+        },
+        sample: SampleOptions {
+            rows: args.sample_rows,
+            rules: args.sample.clone(),
+        },
+        summarize_over: args.summarize_over,
+    };
+    let mut summary = Summary::new(args.top);
+    for skip in &args.unpacked_skipped {
+        let rel_path = skip.path.strip_prefix(args.path()).unwrap_or(&skip.path);
+        summary.record_skip(rel_path, skip.reason);
+    }
+    let (output, manifest) = if args.tree {
+        let tree_options = TreeOptions {
+            format: args.tree_format,
+            annotate: args.annotate,
+            depth: args.depth,
+            dirs_only: args.dirs_only,
+            collapse: args.collapse,
+            empty_dirs: args.empty_dirs,
+            show_skipped: args.show_ignored,
+            languages: args.languages()?,
+        };
+        (get_tree(args.path(), excludes, walk, &tree_options)?, None)
+    } else {
+        let (markdown, manifest) = get_contents(args, walk, excludes, &options, &mut summary)?;
+        (markdown, Some(manifest))
+    };
+
+    emit(args, output)?;
+
+    // Remember what was copied for a later `--since-last`.
+    if let Some(mut manifest) = manifest {
+        manifest.selection = args.selection_flags();
+        if let Err(err) = args.manifests().and_then(|store| store.record(&manifest)) {
+            eprintln!("warning: failed to record the copied files: {}", err);
+        }
+    }
+
+    if !args.tree && !args.no_summary {
+        print_summary(&summary, args.stats_json)
+            .map_err(|e| anyhow::anyhow!("failed to print summary: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Watch the collected directory and call [`run`] again whenever one of the
+/// collected files is added, modified or removed.
+fn run_watch(args: &Args, walk: &WalkOptions, excludes: &[String]) -> Result<()> {
+    let root = args
+        .path()
+        .canonicalize()
+        .map_err(|e| anyhow::anyhow!("failed to resolve {}: {}", args.path().display(), e))?;
+    let mut files = files::collect_files(args.path().to_path_buf(), excludes, true, walk)?;
+
+    eprintln!("watching {} for changes", args.path().display());
+    watch::watch(&root, Duration::from_millis(args.debounce), |touched| {
+        // The watcher reports canonical paths; map them back onto the
+        // path as given so they compare equal to the collected files.
+        let touched: Vec<PathBuf> = touched
+            .iter()
+            .filter_map(|p| p.strip_prefix(&root).ok())
+            .map(|rel| args.path().join(rel))
+            .collect();
+        let current = files::collect_files(args.path().to_path_buf(), excludes, true, walk)?;
+        let changes = Changes::between(&files, &current, &touched);
+        files = current;
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+        eprintln!("changed: {}", changes.describe(args.path()));
+        run(args, walk, excludes)
+    })
+}
+
+/// Render the effective language mappings, one per line, marking entries
+/// that come from user mappings.
+fn get_languages() -> String {
+    let mut output = String::new();
+    for mapping in language::mapping_table() {
+        let source = if mapping.user { "  (user)" } else { "" };
+        output.push_str(&format!(
+            "{:<10} {:<32} {}{}\n",
+            mapping.kind, mapping.pattern, mapping.tag, source
+        ));
+    }
+    output
+}
+
+/// Collect the input once (or in dry-run mode, report on it), then keep
+/// watching it if asked to.
+fn copy(args: &Args) -> Result<()> {
+    let walk = args.walk_options();
+    let excludes = args.excludes();
+
+    if args.dry_run {
+        print!(
+            "{}",
+            get_dry_run(
+                args.path(),
+                &excludes,
+                &walk,
+                &args.selection()?,
+                args.grep().as_ref(),
+                &args.unpacked_skipped,
+                args.max_size
+            )?
+        );
+        return Ok(());
+    }
+
+    run(args, &walk, &excludes)?;
+
+    if args.watch {
+        run_watch(args, &walk, &excludes)?;
+    }
+    Ok(())
+}
+
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    if let Err(err) = language::load_user_languages(args.languages_file.as_deref()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+
+    if let Some(Command::Languages) = args.command {
+        print!("{}", get_languages());
+        return;
+    }
 
-    if !args.path.exists() {
-        eprintln!("provided path {} does not exist", args.path.display());
+    if !args.path().exists() {
+        eprintln!("provided path {} does not exist", args.path().display());
         std::process::exit(1);
     }
 
-    let output = if args.tree {
-        get_tree(&args.path, &args.excludes, args.sort)
+    if args.watch && archive::is_archive(args.path()) {
+        eprintln!("--watch cannot be used with an archive");
+        std::process::exit(1);
+    }
+
+    // Archives and git revisions are collected from their unpacked files,
+    // which are removed again before exiting.
+    let unpacked = if let Some(rev) = &args.rev {
+        Some(
+            revision::export_revision(args.path(), rev)
+                .map_err(|e| anyhow::anyhow!("failed to read revision `{}`: {}", rev, e)),
+        )
+    } else if archive::is_archive(args.path()) {
+        Some(Archive::extract(args.path(), args.max_size).map_err(|e| {
+            anyhow::anyhow!("failed to read archive {}: {}", args.path().display(), e)
+        }))
     } else {
-        get_contents(&args.path, &args.excludes, args.sort)
+        None
     };
-
-    let output = match output {
-        Ok(s) => s,
+    let unpacked = match unpacked.transpose() {
+        Ok(unpacked) => unpacked,
         Err(err) => {
             eprintln!("{}", err);
             std::process::exit(1);
         }
     };
+    if let Some(unpacked) = &unpacked {
+        args.source = args.path.replace(unpacked.root().to_path_buf());
+        args.unpacked_skipped = unpacked.skipped().to_vec();
+    }
 
-    if args.print {
-        println!("{}", output);
-    } else {
-        let mut ctx = ClipboardContext::new().unwrap_or_else(|e| {
-            eprintln!("failed to create clipboard context: {}", e);
-            std::process::exit(1);
-        });
-        ctx.set_contents(output).unwrap_or_else(|e| {
-            eprintln!("failed to set clipboard contents: {}", e);
-            std::process::exit(1);
-        });
+    let result = copy(&args);
+    drop(unpacked);
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,245 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Manifests kept per collected root; older ones are removed.
+const KEEP_MANIFESTS: usize = 10;
+
+/// The files of one copy: relative paths and SHA-256 hashes of their
+/// contents.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The collected root, as given on the command line.
+    pub root: String,
+    /// Milliseconds since the Unix epoch when the copy was made.
+    pub created: u128,
+    /// Relative path (with `/` separators) -> hex SHA-256 of the contents.
+    pub files: BTreeMap<String, String>,
+    /// The command-line flags that decided which files were copied.
+    #[serde(default)]
+    pub selection: String,
+}
+
+impl Manifest {
+    /// An empty manifest created now.
+    pub fn new() -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        Manifest {
+            created,
+            ..Default::default()
+        }
+    }
+
+    /// Hash `files` under `root`. Unreadable files are left out.
+    pub fn of(root: &Path, files: &[PathBuf]) -> Self {
+        let mut manifest = Manifest::new();
+        for file in files {
+            if let Ok(contents) = fs::read(file) {
+                manifest.add(root, file, &contents);
+            }
+        }
+        manifest
+    }
+
+    /// Add `file` under `root`, which was read as `contents`.
+    pub fn add(&mut self, root: &Path, file: &Path, contents: &[u8]) {
+        self.files
+            .insert(relative(root, file), hex(&Sha256::digest(contents)));
+    }
+
+    /// The `files` under `root` that were added or modified since this
+    /// manifest, according to `current`.
+    pub fn changed(&self, root: &Path, files: Vec<PathBuf>, current: &Manifest) -> Vec<PathBuf> {
+        files
+            .into_iter()
+            .filter(|file| {
+                let rel = relative(root, file);
+                self.files
+                    .get(&rel)
+                    .is_none_or(|hash| current.files.get(&rel) != Some(hash))
+            })
+            .collect()
+    }
+
+    /// The paths of this manifest that no longer exist under `root`.
+    pub fn deleted(&self, root: &Path) -> Vec<String> {
+        self.files
+            .keys()
+            .filter(|rel| !root.join(rel).exists())
+            .cloned()
+            .collect()
+    }
+}
+
+/// `file` relative to `root` with `/` separators, or its name when `file`
+/// is the root itself.
+fn relative(root: &Path, file: &Path) -> String {
+    let rel = match file.strip_prefix(root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel,
+        _ => Path::new(file.file_name().unwrap_or(file.as_os_str())),
+    };
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The local state directory: `$XDG_STATE_HOME/copycat`, or
+/// `~/.local/state/copycat`.
+pub fn state_dir() -> Option<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))?;
+    Some(state.join("copycat"))
+}
+
+/// The manifests recorded for one collected root, stored as
+/// `<created>.json` files in a directory named after a hash of the root.
+pub struct ManifestStore {
+    dir: PathBuf,
+    root: String,
+}
+
+impl ManifestStore {
+    /// The store for `root` inside the state directory `state`.
+    pub fn new(state: &Path, root: &Path) -> Self {
+        let root = root.to_string_lossy().into_owned();
+        let key = hex(&Sha256::digest(root.as_bytes())[..8]);
+        ManifestStore {
+            dir: state.join("manifests").join(key),
+            root,
+        }
+    }
+
+    /// The store for `root` inside [`state_dir`].
+    pub fn for_root(root: &Path) -> Result<Self> {
+        let state = state_dir().ok_or_else(|| anyhow!("no state directory, HOME is not set"))?;
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        Ok(ManifestStore::new(&state, &root))
+    }
+
+    /// The most recently recorded manifest, if any.
+    pub fn latest(&self) -> Result<Option<Manifest>> {
+        let Some(latest) = self.recorded()?.pop() else {
+            return Ok(None);
+        };
+        let contents = fs::read_to_string(&latest)?;
+        let manifest = serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("invalid manifest {}: {}", latest.display(), e))?;
+        Ok(Some(manifest))
+    }
+
+    /// Save `manifest` as the latest one and remove all but the most recent
+    /// [`KEEP_MANIFESTS`].
+    pub fn record(&self, manifest: &Manifest) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let manifest = Manifest {
+            root: self.root.clone(),
+            ..manifest.clone()
+        };
+        let file = self.dir.join(format!("{:020}.json", manifest.created));
+        fs::write(file, serde_json::to_string_pretty(&manifest)?)?;
+
+        let recorded = self.recorded()?;
+        for old in &recorded[..recorded.len().saturating_sub(KEEP_MANIFESTS)] {
+            fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    /// The recorded manifest files, oldest first.
+    fn recorded(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_changed_and_deleted() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        let (main, lib, old) = (
+            root.join("src/main.rs"),
+            root.join("lib.rs"),
+            root.join("old.rs"),
+        );
+        fs::write(&main, "fn main() {}\n").unwrap();
+        fs::write(&lib, "pub fn a() {}\n").unwrap();
+        fs::write(&old, "\n").unwrap();
+        let previous = Manifest::of(root, &[main.clone(), lib.clone(), old.clone()]);
+        assert_eq!(
+            previous.files.keys().collect::<Vec<_>>(),
+            vec!["lib.rs", "old.rs", "src/main.rs"]
+        );
+
+        fs::write(&lib, "pub fn b() {}\n").unwrap();
+        fs::remove_file(&old).unwrap();
+        let new = root.join("new.rs");
+        fs::write(&new, "\n").unwrap();
+        let files = vec![main, lib.clone(), new.clone()];
+        let current = Manifest::of(root, &files);
+
+        assert_eq!(previous.changed(root, files, &current), vec![lib, new]);
+        assert_eq!(previous.deleted(root), vec!["old.rs"]);
+    }
+
+    #[test]
+    fn test_store_keeps_latest_manifests() {
+        let state = tempdir().unwrap();
+        let store = ManifestStore::new(state.path(), Path::new("/work/project"));
+        let other = ManifestStore::new(state.path(), Path::new("/work/other"));
+        assert_eq!(store.latest().unwrap(), None);
+
+        for created in 1..=12 {
+            let mut manifest = Manifest {
+                created,
+                selection: "--lang rust".to_string(),
+                ..Default::default()
+            };
+            manifest
+                .files
+                .insert("a.rs".to_string(), format!("hash{}", created));
+            store.record(&manifest).unwrap();
+        }
+
+        let latest = store.latest().unwrap().unwrap();
+        assert_eq!(latest.created, 12);
+        assert_eq!(latest.root, "/work/project");
+        assert_eq!(latest.files["a.rs"], "hash12");
+        assert_eq!(latest.selection, "--lang rust");
+        assert_eq!(store.recorded().unwrap().len(), KEEP_MANIFESTS);
+        assert_eq!(other.latest().unwrap(), None);
+    }
+
+    #[test]
+    fn test_manifest_without_selection() {
+        let manifest: Manifest =
+            serde_json::from_str(r#"{"root": "/work/project", "created": 1, "files": {}}"#)
+                .unwrap();
+        assert_eq!(manifest.selection, "");
+    }
+}
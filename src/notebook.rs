@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::language::normalize_name;
+use crate::transform::{transform, TransformOptions};
+
+/// What to do with the outputs of notebook code cells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NotebookOutputs {
+    /// Keep every output line.
+    Keep,
+    /// Keep the first lines of each output, see
+    /// [`NotebookOptions::output_lines`].
+    #[default]
+    Truncate,
+    /// Leave outputs out.
+    Drop,
+}
+
+/// Options for rendering `.ipynb` notebooks.
+#[derive(Debug, Clone)]
+pub struct NotebookOptions {
+    /// How cell outputs are included.
+    pub outputs: NotebookOutputs,
+    /// Lines kept per output when truncating.
+    pub output_lines: usize,
+    /// Leave image outputs out instead of noting them with a placeholder.
+    pub strip_images: bool,
+}
+
+impl Default for NotebookOptions {
+    fn default() -> Self {
+        NotebookOptions {
+            outputs: NotebookOutputs::default(),
+            output_lines: 20,
+            strip_images: false,
+        }
+    }
+}
+
+/// ANSI escape sequences, which colour error tracebacks.
+static ANSI_ESCAPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+
+/// Render the notebook JSON in `source` as Markdown: Markdown cells as text,
+/// code cells as code blocks in the kernel language followed by their
+/// outputs, and raw cells as plain text blocks. Base64 images are never
+/// embedded.
+///
+/// `transform_options` are applied to the source of code cells.
+pub fn render_notebook(
+    source: &str,
+    options: &NotebookOptions,
+    transform_options: &TransformOptions,
+) -> Result<String> {
+    let notebook: Value = serde_json::from_str(source)?;
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| anyhow!("notebook has no cells"))?;
+    let language = kernel_language(&notebook);
+
+    let mut blocks = Vec::new();
+    for cell in cells {
+        let text = join_text(&cell["source"]);
+        match cell["cell_type"].as_str() {
+            Some("markdown") => {
+                if !text.trim().is_empty() {
+                    blocks.push(text.trim_end().to_string());
+                }
+            }
+            Some("code") => {
+                let code = if transform_options.is_enabled() {
+                    transform(&text, &language, transform_options)
+                } else {
+                    text
+                };
+                blocks.push(format!("```{}\n{}\n```", language, code.trim_end()));
+                if options.outputs != NotebookOutputs::Drop {
+                    if let Some(outputs) = cell["outputs"].as_array() {
+                        blocks.extend(outputs.iter().filter_map(|o| render_output(o, options)));
+                    }
+                }
+            }
+            _ => {
+                if !text.trim().is_empty() {
+                    blocks.push(format!("```text\n{}\n```", text.trim_end()));
+                }
+            }
+        }
+    }
+    Ok(blocks.join("\n\n"))
+}
+
+/// The code block tag of the notebook's kernel language, `python` when the
+/// metadata does not name one.
+fn kernel_language(notebook: &Value) -> String {
+    let metadata = &notebook["metadata"];
+    metadata["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| metadata["language_info"]["name"].as_str())
+        .map_or_else(|| "python".to_string(), normalize_name)
+}
+
+/// Notebook text fields are either a string or a list of lines.
+fn join_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Render one cell output as an `output` block, an image placeholder, or
+/// nothing for outputs without a text form.
+fn render_output(output: &Value, options: &NotebookOptions) -> Option<String> {
+    let text = match output["output_type"].as_str()? {
+        "stream" => join_text(&output["text"]),
+        "error" => {
+            let traceback: Vec<String> = output["traceback"]
+                .as_array()
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+            if traceback.is_empty() {
+                format!(
+                    "{}: {}",
+                    output["ename"].as_str().unwrap_or("Error"),
+                    output["evalue"].as_str().unwrap_or_default()
+                )
+            } else {
+                ANSI_ESCAPE
+                    .replace_all(&traceback.join("\n"), "")
+                    .into_owned()
+            }
+        }
+        _ => {
+            let data = output["data"].as_object()?;
+            let image = data.keys().find(|mime| mime.starts_with("image/"));
+            match ["text/plain", "text/markdown"]
+                .iter()
+                .find_map(|mime| data.get(*mime))
+            {
+                Some(text) if image.is_none() => join_text(text),
+                _ => {
+                    let mime = image.or_else(|| data.keys().next())?;
+                    if options.strip_images && image.is_some() {
+                        return None;
+                    }
+                    return Some(format!("_[{} output omitted]_", mime));
+                }
+            }
+        }
+    };
+
+    let text = text.trim_end();
+    if text.is_empty() {
+        return None;
+    }
+    let text = match options.outputs {
+        NotebookOutputs::Truncate => truncate_lines(text, options.output_lines),
+        _ => text.to_string(),
+    };
+    Some(format!("```output\n{}\n```", text))
+}
+
+/// The first `max` lines of `text`, with a note of how many were cut.
+fn truncate_lines(text: &str, max: usize) -> String {
+    let total = text.lines().count();
+    if total <= max {
+        return text.to_string();
+    }
+    let mut kept: Vec<&str> = text.lines().take(max).collect();
+    let more = total - max;
+    let note = format!(
+        "… ({} more {})",
+        more,
+        if more == 1 { "line" } else { "lines" }
+    );
+    kept.push(&note);
+    kept.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+      "metadata": {
+        "kernelspec": { "name": "python3", "language": "python" }
+      },
+      "nbformat": 4,
+      "cells": [
+        { "cell_type": "markdown", "source": ["# Analysis\n", "Load the data."] },
+        {
+          "cell_type": "code",
+          "source": "import pandas as pd  # tables\ndf = pd.read_csv('a.csv')\nprint(len(df))",
+          "outputs": [
+            { "output_type": "stream", "name": "stdout", "text": ["1\n", "2\n", "3\n"] },
+            {
+              "output_type": "display_data",
+              "data": { "image/png": "iVBORw0KGgo=", "text/plain": ["<Figure>"] }
+            }
+          ]
+        },
+        {
+          "cell_type": "code",
+          "source": ["1 / 0"],
+          "outputs": [
+            {
+              "output_type": "error",
+              "ename": "ZeroDivisionError",
+              "evalue": "division by zero",
+              "traceback": ["\u001b[0;31mZeroDivisionError\u001b[0m: division by zero"]
+            }
+          ]
+        },
+        { "cell_type": "raw", "source": "" }
+      ]
+    }"##;
+
+    #[test]
+    fn test_render_notebook() {
+        let options = NotebookOptions {
+            outputs: NotebookOutputs::Keep,
+            ..Default::default()
+        };
+        let rendered = render_notebook(NOTEBOOK, &options, &TransformOptions::default()).unwrap();
+        assert_eq!(
+            rendered,
+            "# Analysis\nLoad the data.\n\n\
+             ```python\nimport pandas as pd  # tables\ndf = pd.read_csv('a.csv')\nprint(len(df))\n```\n\n\
+             ```output\n1\n2\n3\n```\n\n\
+             _[image/png output omitted]_\n\n\
+             ```python\n1 / 0\n```\n\n\
+             ```output\nZeroDivisionError: division by zero\n```"
+        );
+        assert!(!rendered.contains("iVBORw0KGgo"));
+    }
+
+    #[test]
+    fn test_render_notebook_options() {
+        let options = NotebookOptions {
+            outputs: NotebookOutputs::Truncate,
+            output_lines: 2,
+            strip_images: true,
+        };
+        let transform = TransformOptions {
+            strip_comments: true,
+            ..Default::default()
+        };
+        let rendered = render_notebook(NOTEBOOK, &options, &transform).unwrap();
+        assert!(rendered.contains("```python\nimport pandas as pd\n"));
+        assert!(rendered.contains("```output\n1\n2\n… (1 more line)\n```"));
+        assert!(!rendered.contains("image/png"));
+
+        let options = NotebookOptions {
+            outputs: NotebookOutputs::Drop,
+            ..Default::default()
+        };
+        let rendered = render_notebook(NOTEBOOK, &options, &TransformOptions::default()).unwrap();
+        assert!(!rendered.contains("```output"));
+        assert!(!rendered.contains("omitted"));
+    }
+
+    #[test]
+    fn test_kernel_language() {
+        let notebook = r#"{"metadata": {"language_info": {"name": "R"}}, "cells": [
+            {"cell_type": "code", "source": "x <- 1", "outputs": []}
+        ]}"#;
+        let rendered = render_notebook(
+            notebook,
+            &NotebookOptions::default(),
+            &TransformOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(rendered, "```r\nx <- 1\n```");
+        assert!(render_notebook(
+            "{}",
+            &NotebookOptions::default(),
+            &TransformOptions::default()
+        )
+        .is_err());
+    }
+}
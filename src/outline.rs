@@ -0,0 +1,302 @@
+use tree_sitter::{Language, Node, Parser};
+
+/// Placeholder for an elided brace-delimited body.
+const ELIDED_BLOCK: &str = "{ … }";
+
+/// Placeholder for an elided Python body.
+const ELIDED_SUITE: &str = "...";
+
+/// How one language is outlined: its grammar and the node kinds whose
+/// `body` field is elided.
+struct Grammar {
+    language: Language,
+    bodies: &'static [&'static str],
+}
+
+const RUST_BODIES: &[&str] = &["function_item"];
+const PYTHON_BODIES: &[&str] = &["function_definition"];
+const JS_BODIES: &[&str] = &[
+    "function_declaration",
+    "function_expression",
+    "generator_function_declaration",
+    "generator_function",
+    "method_definition",
+    "arrow_function",
+];
+const GO_BODIES: &[&str] = &["function_declaration", "method_declaration"];
+const JAVA_BODIES: &[&str] = &["method_declaration", "constructor_declaration"];
+
+/// The grammar for a code block tag returned by
+/// [`crate::language::detect_language`], if outlining supports it.
+fn grammar(language: &str) -> Option<Grammar> {
+    let (language, bodies) = match language {
+        "rust" => (tree_sitter_rust::LANGUAGE.into(), RUST_BODIES),
+        "python" => (tree_sitter_python::LANGUAGE.into(), PYTHON_BODIES),
+        "typescript" => (
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            JS_BODIES,
+        ),
+        "tsx" => (tree_sitter_typescript::LANGUAGE_TSX.into(), JS_BODIES),
+        "javascript" | "jsx" => (tree_sitter_javascript::LANGUAGE.into(), JS_BODIES),
+        "go" => (tree_sitter_go::LANGUAGE.into(), GO_BODIES),
+        "java" => (tree_sitter_java::LANGUAGE.into(), JAVA_BODIES),
+        _ => return None,
+    };
+    Some(Grammar { language, bodies })
+}
+
+/// Reduce `source` to its API surface: type definitions, signatures,
+/// trait/impl/class headers and doc comments, with function bodies elided
+/// as `{ … }` (or `...` in Python, after any docstring).
+///
+/// Returns `None` if `language` is not supported or cannot be parsed, in
+/// which case callers should fall back to the full content.
+pub fn outline(source: &str, language: &str) -> Option<String> {
+    let grammar = grammar(language)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language).ok()?;
+    let tree = parser.parse(source, None)?;
+
+    let mut elisions = Vec::new();
+    collect_elisions(tree.root_node(), source, &grammar, &mut elisions);
+
+    let mut output = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (start, end, replacement) in elisions {
+        output.push_str(&source[pos..start]);
+        output.push_str(&replacement);
+        pos = end;
+    }
+    output.push_str(&source[pos..]);
+    Some(output)
+}
+
+/// Collect the byte ranges of elided bodies below `node`, in source order,
+/// with the text replacing each.
+fn collect_elisions(
+    node: Node<'_>,
+    source: &str,
+    grammar: &Grammar,
+    out: &mut Vec<(usize, usize, String)>,
+) {
+    if grammar.bodies.contains(&node.kind()) {
+        if let Some(body) = node.child_by_field_name("body") {
+            if let Some(elision) = elide(body, source) {
+                out.push(elision);
+            }
+            return;
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_elisions(child, source, grammar, out);
+    }
+}
+
+/// The replacement for one `body` node.
+///
+/// Brace-delimited bodies become `{ … }`. Python blocks keep a leading
+/// docstring and end in `...`; arrow functions with expression bodies are
+/// left alone.
+fn elide(body: Node<'_>, source: &str) -> Option<(usize, usize, String)> {
+    match body.kind() {
+        "block" if !source[body.byte_range()].starts_with('{') => {
+            let mut start = body.start_byte();
+            let mut replacement = String::new();
+            if let Some(doc) = docstring(body) {
+                let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+                let indent = &source[line_start..start];
+                replacement = format!("{}\n{}", &source[doc.byte_range()], indent);
+                start = doc.start_byte();
+            }
+            replacement.push_str(ELIDED_SUITE);
+            Some((start, body.end_byte(), replacement))
+        }
+        _ if source[body.byte_range()].starts_with('{') => {
+            Some((body.start_byte(), body.end_byte(), ELIDED_BLOCK.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// The docstring statement opening a Python block, if any.
+fn docstring(block: Node<'_>) -> Option<Node<'_>> {
+    let first = block.named_child(0)?;
+    let is_string = first.kind() == "expression_statement"
+        && first.named_child(0).is_some_and(|n| n.kind() == "string");
+    is_string.then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_rust() {
+        let source = r#"/// A point.
+pub struct Point {
+    pub x: i32,
+}
+
+impl Point {
+    /// Create a point.
+    pub fn new(x: i32) -> Self {
+        Point { x }
+    }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str {
+        "shape"
+    }
+}
+"#;
+        let expected = r#"/// A point.
+pub struct Point {
+    pub x: i32,
+}
+
+impl Point {
+    /// Create a point.
+    pub fn new(x: i32) -> Self { … }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &str { … }
+}
+"#;
+        assert_eq!(outline(source, "rust").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_outline_python_keeps_docstrings() {
+        let source = r#"class Greeter:
+    """Says hello."""
+
+    def greet(self, name):
+        """Greet `name`."""
+        message = f"hello {name}"
+        return message
+
+    def shout(self):
+        return "HI"
+"#;
+        let expected = r#"class Greeter:
+    """Says hello."""
+
+    def greet(self, name):
+        """Greet `name`."""
+        ...
+
+    def shout(self):
+        ...
+"#;
+        assert_eq!(outline(source, "python").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_outline_typescript_and_javascript() {
+        let source = r#"export interface User {
+  name: string;
+}
+
+export function greet(user: User): string {
+  return `hi ${user.name}`;
+}
+
+export class Service {
+  run(): void {
+    console.log("run");
+  }
+}
+
+const double = (x: number) => x * 2;
+const handler = () => {
+  work();
+};
+"#;
+        let expected = r#"export interface User {
+  name: string;
+}
+
+export function greet(user: User): string { … }
+
+export class Service {
+  run(): void { … }
+}
+
+const double = (x: number) => x * 2;
+const handler = () => { … };
+"#;
+        assert_eq!(outline(source, "typescript").unwrap(), expected);
+
+        let js = "function add(a, b) {\n  return a + b;\n}\n";
+        assert_eq!(
+            outline(js, "javascript").unwrap(),
+            "function add(a, b) { … }\n"
+        );
+
+        let tsx = "export function App(): JSX.Element {\n  return <div>{1}</div>;\n}\n";
+        assert_eq!(
+            outline(tsx, "tsx").unwrap(),
+            "export function App(): JSX.Element { … }\n"
+        );
+    }
+
+    #[test]
+    fn test_outline_go_and_java() {
+        let go = r#"package main
+
+// Server serves.
+type Server struct {
+	Addr string
+}
+
+func (s *Server) Start() error {
+	return nil
+}
+"#;
+        let expected = r#"package main
+
+// Server serves.
+type Server struct {
+	Addr string
+}
+
+func (s *Server) Start() error { … }
+"#;
+        assert_eq!(outline(go, "go").unwrap(), expected);
+
+        let java = r#"public class App {
+    private int count;
+
+    public App() {
+        count = 0;
+    }
+
+    /** Run it. */
+    public void run() {
+        count++;
+    }
+}
+"#;
+        let expected = r#"public class App {
+    private int count;
+
+    public App() { … }
+
+    /** Run it. */
+    public void run() { … }
+}
+"#;
+        assert_eq!(outline(java, "java").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_outline_unsupported_language() {
+        assert!(outline("key = 1\n", "toml").is_none());
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::stats::estimate_tokens;
+
+/// BM25 term frequency saturation.
+const K1: f64 = 1.2;
+/// BM25 document length normalization.
+const B: f64 = 0.75;
+/// Path terms count this many times, since a file named after a concept is
+/// usually about it.
+const PATH_WEIGHT: usize = 3;
+
+/// Common English words that carry no meaning in a task description.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "into", "is", "it", "of",
+    "on", "or", "that", "the", "this", "to", "with",
+];
+
+/// Query-driven file selection selected with `--rank`.
+#[derive(Debug, Clone)]
+pub struct RankOptions {
+    /// Natural-language description of the task.
+    pub query: String,
+    /// Keep at most this many files.
+    pub top: Option<usize>,
+    /// Keep the best files whose estimated tokens fit in this budget.
+    pub token_budget: Option<usize>,
+}
+
+/// Split `text` into lowercase, stemmed terms, breaking identifiers on
+/// `snake_case`, `kebab-case`, `camelCase`, `HTTPServer`-style acronyms and
+/// digits.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = word.chars().collect();
+        let mut start = 0;
+        for i in 1..=chars.len() {
+            let boundary = i == chars.len() || {
+                let (prev, cur) = (chars[i - 1], chars[i]);
+                let next_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+                (prev.is_lowercase() && cur.is_uppercase())
+                    || (prev.is_uppercase() && cur.is_uppercase() && next_lower)
+                    || (prev.is_alphabetic() != cur.is_alphabetic())
+            };
+            if boundary {
+                let term = stem(&chars[start..i].iter().collect::<String>().to_lowercase());
+                if term.chars().count() > 1 && !STOPWORDS.contains(&term.as_str()) {
+                    terms.push(term);
+                }
+                start = i;
+            }
+        }
+    }
+    terms
+}
+
+/// Strip a plural or verb suffix so `sorting`, `sorted` and `sorts` all
+/// become `sort`.
+fn stem(term: &str) -> String {
+    for suffix in ["ing", "ed", "s"] {
+        if let Some(base) = term.strip_suffix(suffix) {
+            if base.chars().count() >= 3 && !base.ends_with('s') {
+                return base.to_string();
+            }
+        }
+    }
+    term.to_string()
+}
+
+/// A BM25 index over the terms of a set of documents.
+struct Index {
+    /// Term frequencies per document.
+    docs: Vec<HashMap<String, usize>>,
+    /// Number of terms per document.
+    lengths: Vec<usize>,
+    /// Number of documents containing each term.
+    doc_freq: HashMap<String, usize>,
+}
+
+impl Index {
+    fn new(documents: impl IntoIterator<Item = Vec<String>>) -> Self {
+        let mut index = Index {
+            docs: Vec::new(),
+            lengths: Vec::new(),
+            doc_freq: HashMap::new(),
+        };
+        for terms in documents {
+            let mut freqs: HashMap<String, usize> = HashMap::new();
+            for term in &terms {
+                *freqs.entry(term.clone()).or_default() += 1;
+            }
+            for term in freqs.keys() {
+                *index.doc_freq.entry(term.clone()).or_default() += 1;
+            }
+            index.lengths.push(terms.len());
+            index.docs.push(freqs);
+        }
+        index
+    }
+
+    /// BM25 score of every document for `query` terms.
+    fn scores(&self, query: &[String]) -> Vec<f64> {
+        let n = self.docs.len() as f64;
+        let avg_len = self.lengths.iter().sum::<usize>() as f64 / n.max(1.0);
+        self.docs
+            .iter()
+            .zip(&self.lengths)
+            .map(|(freqs, &len)| {
+                query
+                    .iter()
+                    .map(|term| {
+                        let Some(&tf) = freqs.get(term) else {
+                            return 0.0;
+                        };
+                        let df = self.doc_freq[term] as f64;
+                        let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                        let tf = tf as f64;
+                        let norm = 1.0 - B + B * len as f64 / avg_len.max(1.0);
+                        idf * tf * (K1 + 1.0) / (tf + K1 * norm)
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// Rank `files` by relevance to `options.query` and keep the best ones,
+/// most relevant first.
+///
+/// Each file is indexed by the terms of its path relative to `root` and of
+/// its contents (identifiers, comments and strings alike). Files that share
+/// no term with the query are dropped; unreadable files are ranked by path
+/// alone.
+pub fn rank_files(root: &Path, files: Vec<PathBuf>, options: &RankOptions) -> Vec<PathBuf> {
+    let contents: Vec<String> = files
+        .iter()
+        .map(|file| fs::read_to_string(file).unwrap_or_default())
+        .collect();
+    let index = Index::new(files.iter().zip(&contents).map(|(file, text)| {
+        let rel = file.strip_prefix(root).unwrap_or(file);
+        let path_terms = tokenize(&rel.to_string_lossy());
+        let mut terms: Vec<String> = std::iter::repeat_n(path_terms, PATH_WEIGHT)
+            .flatten()
+            .collect();
+        terms.extend(tokenize(text));
+        terms
+    }));
+
+    let mut query = tokenize(&options.query);
+    query.sort();
+    query.dedup();
+    let scores = index.scores(&query);
+
+    let mut ranked: Vec<usize> = (0..files.len()).filter(|&i| scores[i] > 0.0).collect();
+    ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]).then(a.cmp(&b)));
+    if let Some(top) = options.top {
+        ranked.truncate(top);
+    }
+    if let Some(budget) = options.token_budget {
+        let mut used = 0;
+        ranked.retain(|&i| {
+            let tokens = estimate_tokens(&contents[i]);
+            let fits = used + tokens <= budget;
+            if fits {
+                used += tokens;
+            }
+            fits
+        });
+    }
+
+    let mut files: Vec<Option<PathBuf>> = files.into_iter().map(Some).collect();
+    ranked.into_iter().filter_map(|i| files[i].take()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_tokenize_splits_identifiers() {
+        assert_eq!(
+            tokenize("fix the TreeNode sort_children in parseHTTPServers2 kebab-case sorting"),
+            vec![
+                "fix", "tree", "node", "sort", "children", "parse", "http", "server", "kebab",
+                "case", "sort"
+            ]
+        );
+    }
+
+    fn sample(root: &Path) -> Vec<PathBuf> {
+        let files = [
+            (
+                "src/tree.rs",
+                "/// Sort the tree children by name.\nfn sort_children(node: &mut TreeNode) {}\n",
+            ),
+            (
+                "src/watch.rs",
+                "// Watch files for changes.\nfn watch(root: &Path) {}\n",
+            ),
+            (
+                "src/stats.rs",
+                "fn estimate_tokens(text: &str) -> usize { text.len() / 4 }\n",
+            ),
+            ("README.md", "A tool that copies source code and a tree.\n"),
+        ];
+        files
+            .iter()
+            .map(|(rel, text)| {
+                let path = root.join(rel);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, text).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn rank(root: &Path, files: &[PathBuf], options: RankOptions) -> Vec<String> {
+        rank_files(root, files.to_vec(), &options)
+            .iter()
+            .map(|p| p.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rank_by_relevance() {
+        let dir = tempdir().unwrap();
+        let files = sample(dir.path());
+
+        let options = RankOptions {
+            query: "fix the tree sorting bug".to_string(),
+            top: None,
+            token_budget: None,
+        };
+        assert_eq!(
+            rank(dir.path(), &files, options),
+            vec!["src/tree.rs", "README.md"]
+        );
+
+        let options = RankOptions {
+            query: "token estimates and tree".to_string(),
+            top: Some(1),
+            token_budget: None,
+        };
+        assert_eq!(rank(dir.path(), &files, options), vec!["src/stats.rs"]);
+    }
+
+    #[test]
+    fn test_rank_token_budget() {
+        let dir = tempdir().unwrap();
+        let files = sample(dir.path());
+
+        // The tree file is ~20 tokens and the README ~11.
+        let options = RankOptions {
+            query: "tree".to_string(),
+            top: None,
+            token_budget: Some(25),
+        };
+        assert_eq!(rank(dir.path(), &files, options), vec!["src/tree.rs"]);
+
+        let options = RankOptions {
+            query: "tree".to_string(),
+            top: None,
+            token_budget: Some(40),
+        };
+        assert_eq!(
+            rank(dir.path(), &files, options),
+            vec!["src/tree.rs", "README.md"]
+        );
+    }
+}
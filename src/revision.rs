@@ -0,0 +1,317 @@
+use anyhow::{anyhow, Result};
+use git2::{ObjectType, Oid, Repository, Tree, TreeWalkMode, TreeWalkResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive::{is_safe_entry, Archive};
+use crate::files::COPYCAT_IGNORE;
+
+/// Ignore files exported from the directories above a collected
+/// subdirectory, so their rules as of the revision still apply.
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", COPYCAT_IGNORE];
+
+/// File mode of symbolic links in git trees.
+const SYMLINK_MODE: i32 = 0o120000;
+
+/// Export the files of `rev` (a commit, tag or branch) in the repository
+/// containing `path`, reading trees and blobs from the object database so
+/// the working tree, index and `HEAD` are left alone. Works on bare
+/// repositories too.
+///
+/// When `path` is a subdirectory or a file of the working tree, only that
+/// part of the revision is exported, together with the ignore files of the
+/// directories above it. The files land in an [`Archive`] laid out like the
+/// repository, whose root is `path`, so `.gitignore` files as of `rev` and
+/// the usual excludes apply when it is collected. Symbolic links and
+/// submodules are left out.
+pub fn export_revision(path: &Path, rev: &str) -> Result<Archive> {
+    let repo = Repository::discover(path).map_err(|e| {
+        anyhow!(
+            "{} is not in a git repository: {}",
+            path.display(),
+            e.message()
+        )
+    })?;
+    let tree = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|e| anyhow!("unknown revision `{}`: {}", rev, e.message()))?;
+
+    let prefix = subdirectory(&repo, path)?;
+    let archive = Archive::empty(&root_name(&repo))?;
+    let repo_root = archive.root().to_path_buf();
+
+    for dir in prefix.ancestors().skip(1) {
+        for name in IGNORE_FILES {
+            if let Ok(entry) = tree.get_path(&dir.join(name)) {
+                if entry.kind() == Some(ObjectType::Blob) && entry.filemode() != SYMLINK_MODE {
+                    write_blob(&repo, entry.id(), &repo_root.join(dir).join(name))?;
+                }
+            }
+        }
+    }
+
+    if prefix.as_os_str().is_empty() {
+        write_tree(&repo, &tree, &repo_root)?;
+        return Ok(archive);
+    }
+    let entry = tree
+        .get_path(&prefix)
+        .map_err(|_| anyhow!("{} does not exist in `{}`", prefix.display(), rev))?;
+    let target = repo_root.join(&prefix);
+    match entry.kind() {
+        Some(ObjectType::Tree) => {
+            let subtree = repo.find_tree(entry.id())?;
+            fs::create_dir_all(&target)?;
+            write_tree(&repo, &subtree, &target)?;
+        }
+        Some(ObjectType::Blob) if entry.filemode() != SYMLINK_MODE => {
+            write_blob(&repo, entry.id(), &target)?;
+        }
+        _ => anyhow::bail!(
+            "{} is not a file or directory in `{}`",
+            prefix.display(),
+            rev
+        ),
+    }
+    Ok(archive.with_root(&prefix))
+}
+
+/// Write the regular and executable files of `tree` below `dir`.
+fn write_tree(repo: &Repository, tree: &Tree, dir: &Path) -> Result<()> {
+    let mut result = Ok(());
+    tree.walk(TreeWalkMode::PreOrder, |parent, entry| {
+        if entry.kind() != Some(ObjectType::Blob) || entry.filemode() == SYMLINK_MODE {
+            return TreeWalkResult::Ok;
+        }
+        let Ok(name) = entry.name() else {
+            return TreeWalkResult::Ok;
+        };
+        let rel = Path::new(parent).join(name);
+        if !is_safe_entry(&rel) {
+            return TreeWalkResult::Ok;
+        }
+        match write_blob(repo, entry.id(), &dir.join(&rel)) {
+            Ok(()) => TreeWalkResult::Ok,
+            Err(err) => {
+                result = Err(err);
+                TreeWalkResult::Abort
+            }
+        }
+    })?;
+    result
+}
+
+/// Write the contents of the blob `id` to `target`.
+fn write_blob(repo: &Repository, id: Oid, target: &Path) -> Result<()> {
+    let blob = repo.find_blob(id)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(target, blob.content())?;
+    Ok(())
+}
+
+/// `path` relative to the repository's working tree; empty for the working
+/// tree root and for bare repositories.
+fn subdirectory(repo: &Repository, path: &Path) -> Result<PathBuf> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(PathBuf::new());
+    };
+    let path = path.canonicalize()?;
+    let workdir = workdir.canonicalize()?;
+    Ok(path
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .unwrap_or_default())
+}
+
+/// The name of the exported repository root: the working tree's directory
+/// name, or the repository's name without `.git` for bare repositories.
+fn root_name(repo: &Repository) -> String {
+    let dir = repo.workdir().unwrap_or(repo.path());
+    let name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_default();
+    match name.strip_suffix(".git") {
+        Some(stem) if !stem.is_empty() => stem.to_string(),
+        _ if name.is_empty() => "repository".to_string(),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::files::{collect_files, WalkOptions};
+    use git2::{Oid, Signature};
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    /// Write `files` (with `/`-separated paths) as a tree object.
+    fn write_tree(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let mut builder = repo.treebuilder(None).unwrap();
+        let mut dirs: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+        for &(path, contents) in files {
+            match path.split_once('/') {
+                Some((dir, rest)) => dirs.entry(dir).or_default().push((rest, contents)),
+                None => {
+                    let blob = repo.blob(contents.as_bytes()).unwrap();
+                    builder.insert(path, blob, 0o100644).unwrap();
+                }
+            }
+        }
+        for (dir, files) in dirs {
+            let tree = write_tree(repo, &files);
+            builder.insert(dir, tree, 0o040000).unwrap();
+        }
+        builder.write().unwrap()
+    }
+
+    /// Commit `files` on top of `HEAD`.
+    fn commit(repo: &Repository, files: &[(&str, &str)]) -> Oid {
+        let tree = repo.find_tree(write_tree(repo, files)).unwrap();
+        let signature = Signature::now("copycat", "copycat@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "commit",
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn collected(archive: &Archive) -> Vec<(String, String)> {
+        collect_files(
+            archive.root().to_path_buf(),
+            &[],
+            true,
+            &WalkOptions::default(),
+        )
+        .unwrap()
+        .iter()
+        .map(|f| {
+            let rel = f.strip_prefix(archive.root()).unwrap();
+            (rel.display().to_string(), fs::read_to_string(f).unwrap())
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_export_tag_from_bare_repository() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path().join("project.git")).unwrap();
+        let v1 = commit(
+            &repo,
+            &[
+                (".gitignore", "*.log\n"),
+                ("src/main.rs", "fn main() {}\n"),
+                ("debug.log", "tracked anyway\n"),
+            ],
+        );
+        repo.tag_lightweight("v1", &repo.find_object(v1, None).unwrap(), false)
+            .unwrap();
+        commit(&repo, &[("src/main.rs", "fn main() { v2() }\n")]);
+
+        let archive = export_revision(&dir.path().join("project.git"), "v1").unwrap();
+        assert!(archive.root().ends_with("project"));
+        assert_eq!(
+            collected(&archive),
+            vec![
+                (".gitignore".to_string(), "*.log\n".to_string()),
+                ("src/main.rs".to_string(), "fn main() {}\n".to_string()),
+            ]
+        );
+
+        let head = export_revision(&dir.path().join("project.git"), "HEAD").unwrap();
+        assert_eq!(
+            collected(&head),
+            vec![(
+                "src/main.rs".to_string(),
+                "fn main() { v2() }\n".to_string()
+            )]
+        );
+        assert!(export_revision(&dir.path().join("project.git"), "v9").is_err());
+    }
+
+    #[test]
+    fn test_export_subdirectory_ignores_working_tree() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(
+            &repo,
+            &[
+                ("src/lib.rs", "pub fn v1() {}\n"),
+                ("docs/guide.md", "# Guide\n"),
+            ],
+        );
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "pub fn edited() {}\n").unwrap();
+        fs::write(dir.path().join("src/new.rs"), "\n").unwrap();
+
+        let archive = export_revision(&dir.path().join("src"), "HEAD").unwrap();
+        assert!(archive.root().ends_with("src"));
+        assert_eq!(
+            collected(&archive),
+            vec![("lib.rs".to_string(), "pub fn v1() {}\n".to_string())]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(),
+            "pub fn edited() {}\n"
+        );
+    }
+
+    #[test]
+    fn test_export_subdirectory_keeps_parent_ignore_files() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(
+            &repo,
+            &[
+                (".gitignore", "*.log\n"),
+                ("src/lib.rs", "pub fn v1() {}\n"),
+                ("src/debug.log", "noise\n"),
+            ],
+        );
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+
+        let archive = export_revision(&dir.path().join("src"), "HEAD").unwrap();
+        assert_eq!(
+            collected(&archive),
+            vec![("lib.rs".to_string(), "pub fn v1() {}\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_export_single_file() {
+        let dir = tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, &[("src/lib.rs", "pub fn v1() {}\n")]);
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "pub fn edited() {}\n").unwrap();
+
+        let archive = export_revision(&dir.path().join("src/lib.rs"), "HEAD").unwrap();
+        let files = collect_files(
+            archive.root().to_path_buf(),
+            &[],
+            true,
+            &WalkOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(files, vec![archive.root().to_path_buf()]);
+        assert_eq!(
+            fs::read_to_string(archive.root()).unwrap(),
+            "pub fn v1() {}\n"
+        );
+
+        fs::create_dir_all(dir.path().join("new")).unwrap();
+        let err = export_revision(&dir.path().join("new"), "HEAD").unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+    }
+}
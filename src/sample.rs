@@ -0,0 +1,446 @@
+use globset::{Glob, GlobMatcher};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::stats::plural;
+
+/// A `--sample PATTERN=ROWS` rule: data files whose relative path matches
+/// `pattern` keep `rows` rows at each end, or are embedded in full when
+/// `rows` is `None` (`all`).
+#[derive(Debug, Clone)]
+pub struct SampleRule {
+    pattern: GlobMatcher,
+    rows: Option<usize>,
+}
+
+impl FromStr for SampleRule {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (pattern, rows) = rule
+            .rsplit_once('=')
+            .ok_or_else(|| format!("expected PATTERN=ROWS, got `{}`", rule))?;
+        let rows = match rows {
+            "all" => None,
+            n => Some(
+                n.parse()
+                    .map_err(|_| format!("expected a row count or `all`, got `{}`", n))?,
+            ),
+        };
+        let glob = Glob::new(pattern).map_err(|e| format!("invalid glob `{}`: {}", pattern, e))?;
+        Ok(SampleRule {
+            pattern: glob.compile_matcher(),
+            rows,
+        })
+    }
+}
+
+/// How data files (CSV, TSV and JSON Lines) are sampled.
+#[derive(Debug, Clone)]
+pub struct SampleOptions {
+    /// Rows kept at the start and at the end of each data file.
+    pub rows: usize,
+    /// Per-pattern overrides of `rows`; later rules win.
+    pub rules: Vec<SampleRule>,
+}
+
+impl Default for SampleOptions {
+    fn default() -> Self {
+        SampleOptions {
+            rows: 5,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl SampleOptions {
+    /// Rows kept at each end of the data file at `rel_path`, or `None` when
+    /// it is embedded in full.
+    pub fn rows_for(&self, rel_path: &Path) -> Option<usize> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.pattern.is_match(rel_path))
+            .map_or(Some(self.rows), |rule| rule.rows)
+    }
+}
+
+/// The type inferred for the values of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnType {
+    /// Only empty or null values so far.
+    Empty,
+    Boolean,
+    Integer,
+    Float,
+    Date,
+    DateTime,
+    String,
+    Array,
+    Object,
+    /// Incompatible JSON types, e.g. strings and arrays.
+    Mixed,
+}
+
+impl ColumnType {
+    fn name(self) -> &'static str {
+        match self {
+            ColumnType::Empty => "empty",
+            ColumnType::Boolean => "boolean",
+            ColumnType::Integer => "integer",
+            ColumnType::Float => "float",
+            ColumnType::Date => "date",
+            ColumnType::DateTime => "datetime",
+            ColumnType::String => "string",
+            ColumnType::Array => "array",
+            ColumnType::Object => "object",
+            ColumnType::Mixed => "mixed",
+        }
+    }
+
+    /// The narrowest type holding values of both `self` and `other`.
+    fn merge(self, other: ColumnType) -> ColumnType {
+        use ColumnType::*;
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Empty, t) | (t, Empty) => t,
+            (Integer, Float) | (Float, Integer) => Float,
+            (Date, DateTime) | (DateTime, Date) => DateTime,
+            (Array | Object | Mixed, _) | (_, Array | Object | Mixed) => Mixed,
+            _ => String,
+        }
+    }
+}
+
+static DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap());
+static DATE_TIME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:?\d{2})?$").unwrap()
+});
+
+/// The type of a delimited text value.
+fn classify_text(value: &str) -> ColumnType {
+    let value = value.trim();
+    if value.is_empty() {
+        ColumnType::Empty
+    } else if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+        ColumnType::Boolean
+    } else if value.parse::<i64>().is_ok() {
+        ColumnType::Integer
+    } else if value.parse::<f64>().is_ok() && value.contains(|c: char| c.is_ascii_digit()) {
+        ColumnType::Float
+    } else if DATE.is_match(value) {
+        ColumnType::Date
+    } else if DATE_TIME.is_match(value) {
+        ColumnType::DateTime
+    } else {
+        ColumnType::String
+    }
+}
+
+/// The type of a JSON value; strings holding dates count as dates.
+fn classify_json(value: &Value) -> ColumnType {
+    match value {
+        Value::Null => ColumnType::Empty,
+        Value::Bool(_) => ColumnType::Boolean,
+        Value::Number(n) if n.is_i64() || n.is_u64() => ColumnType::Integer,
+        Value::Number(_) => ColumnType::Float,
+        Value::String(s) if DATE.is_match(s) => ColumnType::Date,
+        Value::String(s) if DATE_TIME.is_match(s) => ColumnType::DateTime,
+        Value::String(_) => ColumnType::String,
+        Value::Array(_) => ColumnType::Array,
+        Value::Object(_) => ColumnType::Object,
+    }
+}
+
+/// A column's name, its inferred type and whether it has empty values.
+struct Column {
+    name: String,
+    kind: ColumnType,
+    nullable: bool,
+}
+
+impl Column {
+    fn new(name: String) -> Self {
+        Column {
+            name,
+            kind: ColumnType::Empty,
+            nullable: false,
+        }
+    }
+
+    fn observe(&mut self, kind: ColumnType) {
+        if kind == ColumnType::Empty {
+            self.nullable = true;
+        }
+        self.kind = self.kind.merge(kind);
+    }
+}
+
+/// Split one delimited record off the start of `text`, honoring quoted
+/// fields (which may contain delimiters, doubled quotes and newlines).
+/// Returns the record's raw text, its fields and the remaining text.
+fn split_record(text: &str, delimiter: char) -> (&str, Vec<String>, &str) {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek().is_some_and(|&(_, c)| c == '"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' => {
+                fields.push(field);
+                let raw = text[..i].strip_suffix('\r').unwrap_or(&text[..i]);
+                return (raw, fields, &text[i + 1..]);
+            }
+            '\r' => {}
+            c if c == delimiter => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    (text, fields, "")
+}
+
+/// Keeps the first and last `n` rows of a stream of rows.
+struct Rows<'a> {
+    n: usize,
+    count: usize,
+    head: Vec<&'a str>,
+    tail: VecDeque<&'a str>,
+}
+
+impl<'a> Rows<'a> {
+    fn new(n: usize) -> Self {
+        Rows {
+            n,
+            count: 0,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, row: &'a str) {
+        self.count += 1;
+        if self.n == 0 {
+            return;
+        }
+        if self.head.len() < self.n {
+            self.head.push(row);
+        } else {
+            if self.tail.len() == self.n {
+                self.tail.pop_front();
+            }
+            self.tail.push_back(row);
+        }
+    }
+
+    /// The kept rows, with a marker line where rows were left out.
+    fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.head.iter().map(|r| r.to_string()).collect();
+        let omitted = self.count - self.head.len() - self.tail.len();
+        if omitted > 0 {
+            lines.push(format!("… ({} omitted)", plural(omitted, "row")));
+        }
+        lines.extend(self.tail.iter().map(|r| r.to_string()));
+        lines
+    }
+}
+
+/// Render a sample of the data file `contents` detected as `language`
+/// (`csv`, `tsv` or `jsonl`): the header, the first and last `rows` rows,
+/// the row count and the inferred column types. Returns `None` for other
+/// languages.
+pub fn sample_data(contents: &str, language: &str, rows: usize) -> Option<String> {
+    let (kept, columns, count) = match language {
+        "csv" => sample_delimited(contents, ',', rows),
+        "tsv" => sample_delimited(contents, '\t', rows),
+        "jsonl" => sample_json_lines(contents, rows),
+        _ => return None,
+    };
+
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let nullable = if c.nullable && c.kind != ColumnType::Empty {
+                ", nullable"
+            } else {
+                ""
+            };
+            format!("`{}` ({}{})", c.name, c.kind.name(), nullable)
+        })
+        .collect();
+    let shape = if columns.is_empty() {
+        format!("_{}_", plural(count, "row"))
+    } else {
+        format!(
+            "_{}, {}: {}_",
+            plural(count, "row"),
+            plural(columns.len(), "column"),
+            columns.join(", ")
+        )
+    };
+    Some(format!(
+        "```{}\n{}\n```\n\n{}",
+        language,
+        kept.join("\n"),
+        shape
+    ))
+}
+
+/// Sample a delimited file whose first record is the header.
+fn sample_delimited(
+    contents: &str,
+    delimiter: char,
+    n: usize,
+) -> (Vec<String>, Vec<Column>, usize) {
+    let mut rest = contents;
+    let mut header = None;
+    let mut columns: Vec<Column> = Vec::new();
+    let mut rows = Rows::new(n);
+    while !rest.is_empty() {
+        let (raw, fields, next) = split_record(rest, delimiter);
+        rest = next;
+        if raw.trim().is_empty() {
+            continue;
+        }
+        if header.is_none() {
+            header = Some(raw);
+            columns = fields.into_iter().map(Column::new).collect();
+            continue;
+        }
+        for (i, value) in fields.iter().enumerate() {
+            if i == columns.len() {
+                columns.push(Column::new(format!("column{}", i + 1)));
+            }
+            columns[i].observe(classify_text(value));
+        }
+        rows.push(raw);
+    }
+
+    let mut lines: Vec<String> = header.map(String::from).into_iter().collect();
+    lines.extend(rows.lines());
+    (lines, columns, rows.count)
+}
+
+/// Sample a JSON Lines file; object keys become columns, with keys not seen
+/// in earlier rows appended.
+fn sample_json_lines(contents: &str, n: usize) -> (Vec<String>, Vec<Column>, usize) {
+    let mut columns: Vec<Column> = Vec::new();
+    let mut rows = Rows::new(n);
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line) {
+            for (key, value) in &object {
+                let position = columns.iter().position(|c| &c.name == key);
+                let i = position.unwrap_or_else(|| {
+                    let mut column = Column::new(key.clone());
+                    // Earlier rows did not have this key.
+                    column.nullable = rows.count > 0;
+                    columns.push(column);
+                    columns.len() - 1
+                });
+                columns[i].observe(classify_json(value));
+            }
+            for column in columns.iter_mut() {
+                if !object.contains_key(&column.name) {
+                    column.nullable = true;
+                }
+            }
+        }
+        rows.push(line);
+    }
+    (rows.lines(), columns, rows.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_csv() {
+        let mut csv = String::from("id,name,price,added,active\n");
+        for i in 1..=10 {
+            let price = if i == 4 {
+                String::new()
+            } else {
+                format!("{}.5", i)
+            };
+            csv.push_str(&format!(
+                "{},\"Item, number {}\",{},2024-01-{:02},true\n",
+                i, i, price, i
+            ));
+        }
+
+        let sampled = sample_data(&csv, "csv", 2).unwrap();
+        assert_eq!(
+            sampled,
+            "```csv\n\
+             id,name,price,added,active\n\
+             1,\"Item, number 1\",1.5,2024-01-01,true\n\
+             2,\"Item, number 2\",2.5,2024-01-02,true\n\
+             … (6 rows omitted)\n\
+             9,\"Item, number 9\",9.5,2024-01-09,true\n\
+             10,\"Item, number 10\",10.5,2024-01-10,true\n\
+             ```\n\n\
+             _10 rows, 5 columns: `id` (integer), `name` (string), \
+             `price` (float, nullable), `added` (date), `active` (boolean)_"
+        );
+    }
+
+    #[test]
+    fn test_sample_tsv_with_quoted_newlines() {
+        let tsv = "a\tb\n1\t\"two\nlines\"\n2\tx\n";
+        let sampled = sample_data(tsv, "tsv", 5).unwrap();
+        assert_eq!(
+            sampled,
+            "```tsv\na\tb\n1\t\"two\nlines\"\n2\tx\n```\n\n\
+             _2 rows, 2 columns: `a` (integer), `b` (string)_"
+        );
+    }
+
+    #[test]
+    fn test_sample_json_lines() {
+        let jsonl = "{\"id\": 1, \"tags\": [\"a\"], \"at\": \"2024-01-01T10:00:00Z\"}\n\
+                     {\"id\": 2.5, \"tags\": \"b\", \"note\": null}\n\
+                     \n\
+                     {\"id\": 3, \"tags\": [], \"at\": \"2024-01-02\"}\n";
+        let sampled = sample_data(jsonl, "jsonl", 1).unwrap();
+        assert!(sampled.starts_with("```jsonl\n{\"id\": 1,"));
+        assert!(sampled.contains("\n… (1 row omitted)\n{\"id\": 3,"));
+        assert!(sampled.ends_with(
+            "_3 rows, 4 columns: `id` (float), `tags` (mixed), \
+             `at` (datetime, nullable), `note` (empty)_"
+        ));
+        assert_eq!(sample_data("fn main() {}", "rust", 5), None);
+    }
+
+    #[test]
+    fn test_sample_rules() {
+        let options = SampleOptions {
+            rows: 5,
+            rules: vec![
+                "data/**=20".parse().unwrap(),
+                "data/fixtures/*.csv=all".parse().unwrap(),
+            ],
+        };
+        assert_eq!(options.rows_for(Path::new("big.csv")), Some(5));
+        assert_eq!(options.rows_for(Path::new("data/big.csv")), Some(20));
+        assert_eq!(options.rows_for(Path::new("data/fixtures/small.csv")), None);
+        assert!("data/*.csv".parse::<SampleRule>().is_err());
+        assert!("data/*.csv=some".parse::<SampleRule>().is_err());
+    }
+}
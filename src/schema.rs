@@ -0,0 +1,252 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Object keys listed per object before the rest are counted.
+const MAX_KEYS: usize = 40;
+/// Nesting levels rendered before deeper values are elided.
+const MAX_DEPTH: usize = 12;
+/// Characters kept of example string values.
+const MAX_EXAMPLE: usize = 40;
+
+/// The inferred shape of every value seen at one position of a document,
+/// e.g. the `name` field of all elements of an array.
+#[derive(Debug, Default)]
+struct Shape {
+    /// Type names in order of first appearance.
+    types: Vec<&'static str>,
+    /// First scalar value seen, as JSON.
+    example: Option<String>,
+    /// Shortest and longest array seen.
+    lengths: Option<(usize, usize)>,
+    /// Shape of all array elements.
+    items: Option<Box<Shape>>,
+    /// Number of objects seen.
+    objects: usize,
+    /// Shapes of object fields in order of first appearance, with the
+    /// number of objects that had them.
+    fields: Vec<(String, usize, Shape)>,
+    /// Position of each key in `fields`.
+    index: HashMap<String, usize>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        let kind = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_f64() => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        if !self.types.contains(&kind) {
+            self.types.push(kind);
+        }
+
+        match value {
+            Value::Array(items) => {
+                let (min, max) = self.lengths.unwrap_or((items.len(), items.len()));
+                self.lengths = Some((min.min(items.len()), max.max(items.len())));
+                let shape = self.items.get_or_insert_with(Default::default);
+                for item in items {
+                    shape.observe(item);
+                }
+            }
+            Value::Object(object) => {
+                self.objects += 1;
+                for (key, value) in object {
+                    let i = match self.index.get(key) {
+                        Some(&i) => i,
+                        None => {
+                            self.index.insert(key.clone(), self.fields.len());
+                            self.fields.push((key.clone(), 0, Shape::default()));
+                            self.fields.len() - 1
+                        }
+                    };
+                    self.fields[i].1 += 1;
+                    self.fields[i].2.observe(value);
+                }
+            }
+            Value::Null => {}
+            scalar => {
+                if self.example.is_none() {
+                    self.example = Some(example(scalar));
+                }
+            }
+        }
+    }
+
+    /// Render this shape under `name` at `depth`, followed by its fields or
+    /// array elements.
+    fn render(&self, name: &str, optional: bool, depth: usize, lines: &mut Vec<String>) {
+        let indent = "  ".repeat(depth);
+        let mut line = format!("{}{}: {}", indent, name, self.types.join(" | "));
+        if let Some((min, max)) = self.lengths {
+            if min == max {
+                line.push_str(&format!(" [{}]", min));
+            } else {
+                line.push_str(&format!(" [{}-{}]", min, max));
+            }
+        }
+        if optional {
+            line.push_str(" (optional)");
+        }
+        if let Some(example) = &self.example {
+            line.push_str(&format!("  # e.g. {}", example));
+        }
+        lines.push(line);
+
+        if self.fields.is_empty() && self.items.as_ref().is_none_or(|i| i.types.is_empty()) {
+            return;
+        }
+        if depth + 1 >= MAX_DEPTH {
+            lines.push(format!("{}  …", indent));
+            return;
+        }
+        if let Some(items) = self.items.as_ref().filter(|i| !i.types.is_empty()) {
+            items.render("[]", false, depth + 1, lines);
+        }
+        self.render_fields(depth + 1, lines);
+    }
+
+    /// Render the object fields of this shape at `depth`.
+    fn render_fields(&self, depth: usize, lines: &mut Vec<String>) {
+        for (key, count, shape) in self.fields.iter().take(MAX_KEYS) {
+            shape.render(&display_key(key), *count < self.objects, depth, lines);
+        }
+        if self.fields.len() > MAX_KEYS {
+            lines.push(format!(
+                "{}… ({} more keys)",
+                "  ".repeat(depth),
+                self.fields.len() - MAX_KEYS
+            ));
+        }
+    }
+}
+
+/// A short JSON rendering of a scalar example value.
+fn example(value: &Value) -> String {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_EXAMPLE => {
+            let cut: String = s.chars().take(MAX_EXAMPLE).collect();
+            format!("{}…", Value::String(cut))
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Object keys that would be ambiguous in the schema are quoted.
+fn display_key(key: &str) -> String {
+    if key.is_empty() || key.contains([':', '#', '\n']) || key.trim() != key {
+        Value::String(key.to_string()).to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+/// Summarize the structure of the JSON or YAML document in `contents`: its
+/// keys, types, array lengths and an example value per field, with array
+/// elements merged into one shape. Multi-document YAML files are treated as
+/// an array of documents.
+///
+/// Returns `None` for other languages or documents that cannot be parsed.
+pub fn summarize_structure(contents: &str, language: &str) -> Option<String> {
+    let root = match language {
+        "json" => serde_json::from_str(contents).ok()?,
+        "yaml" => {
+            let mut documents = serde_norway::Deserializer::from_str(contents)
+                .map(Value::deserialize)
+                .collect::<Result<Vec<Value>, _>>()
+                .ok()?;
+            if documents.len() == 1 {
+                documents.pop()?
+            } else {
+                Value::Array(documents)
+            }
+        }
+        _ => return None,
+    };
+    let mut shape = Shape::default();
+    shape.observe(&root);
+
+    let mut lines = Vec::new();
+    if matches!(root, Value::Object(_)) {
+        shape.render_fields(0, &mut lines);
+    } else {
+        shape.render("$", false, 0, &mut lines);
+    }
+    Some(format!("```yaml\n{}\n```", lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_json() {
+        let json = r#"{
+            "openapi": "3.0.0",
+            "info": { "title": "Pet Store", "version": 1 },
+            "servers": [],
+            "pets": [
+                { "id": 1, "name": "Rex", "tags": ["dog", "good"] },
+                { "id": 2, "name": "Tom", "tags": [], "age": 3.5 },
+                { "id": 3, "name": null, "tags": ["cat"] }
+            ]
+        }"#;
+        assert_eq!(
+            summarize_structure(json, "json").unwrap(),
+            "```yaml\n\
+             openapi: string  # e.g. \"3.0.0\"\n\
+             info: object\n\
+             \x20 title: string  # e.g. \"Pet Store\"\n\
+             \x20 version: integer  # e.g. 1\n\
+             servers: array [0]\n\
+             pets: array [3]\n\
+             \x20 []: object\n\
+             \x20   id: integer  # e.g. 1\n\
+             \x20   name: string | null  # e.g. \"Rex\"\n\
+             \x20   tags: array [0-2]\n\
+             \x20     []: string  # e.g. \"dog\"\n\
+             \x20   age: number (optional)  # e.g. 3.5\n\
+             ```"
+        );
+    }
+
+    #[test]
+    fn test_summarize_yaml_documents() {
+        let yaml = "apiVersion: v1\nkind: Service\nmetadata:\n  name: web\n---\n\
+                    apiVersion: apps/v1\nkind: Deployment\nspec:\n  replicas: 3\n";
+        assert_eq!(
+            summarize_structure(yaml, "yaml").unwrap(),
+            "```yaml\n\
+             $: array [2]\n\
+             \x20 []: object\n\
+             \x20   apiVersion: string  # e.g. \"v1\"\n\
+             \x20   kind: string  # e.g. \"Service\"\n\
+             \x20   metadata: object (optional)\n\
+             \x20     name: string  # e.g. \"web\"\n\
+             \x20   spec: object (optional)\n\
+             \x20     replicas: integer  # e.g. 3\n\
+             ```"
+        );
+    }
+
+    #[test]
+    fn test_summarize_limits() {
+        let object: serde_json::Map<String, Value> = (0..45)
+            .map(|i| (format!("key{:02}", i), Value::from("x".repeat(50))))
+            .collect();
+        let summary = summarize_structure(&Value::Object(object).to_string(), "json").unwrap();
+        assert!(summary.contains(&format!("key00: string  # e.g. \"{}\"…", "x".repeat(40))));
+        assert!(summary.contains("key39"));
+        assert!(!summary.contains("key40"));
+        assert!(summary.contains("… (5 more keys)"));
+
+        assert_eq!(summarize_structure("{", "json"), None);
+        assert_eq!(summarize_structure("a: [", "yaml"), None);
+        assert_eq!(summarize_structure("{}", "toml"), None);
+    }
+}
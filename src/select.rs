@@ -0,0 +1,230 @@
+use crate::files::{SkipReason, Skipped};
+use crate::follow::{self, Follow};
+use crate::grep::GrepOptions;
+use crate::language::LanguageFilter;
+use crate::manifest::Manifest;
+use crate::rank::{self, RankOptions};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// How the collected files are narrowed down before they are formatted.
+#[derive(Default)]
+pub struct Selection {
+    /// Keep only files of the selected languages.
+    pub languages: Option<LanguageFilter>,
+    /// Keep only the import closure of an entry file.
+    pub follow: Option<Follow>,
+    /// Keep and order files by relevance to a query.
+    pub rank: Option<RankOptions>,
+    /// Keep only files changed since this manifest of the last copy.
+    pub since_last: Option<Manifest>,
+}
+
+/// The files left after [`select_files`], and what happened to the rest.
+#[derive(Debug, Default)]
+pub struct Selected {
+    /// The files to copy, in output order.
+    pub files: Vec<PathBuf>,
+    /// The files dropped along the way, with the step that dropped them.
+    pub skipped: Vec<Skipped>,
+    /// With `--since-last`, the manifest of the selected files before
+    /// narrowing them down to the ones changed since the last copy.
+    pub manifest: Option<Manifest>,
+    /// Files of the last copy that no longer exist.
+    pub deleted: Vec<String>,
+}
+
+impl Selected {
+    /// Why there is nothing to copy, if that is the case.
+    pub fn nothing_left(&self) -> Option<&'static str> {
+        if !self.files.is_empty() || !self.deleted.is_empty() {
+            return None;
+        }
+        // Selection stops at the step that drops the last file.
+        Some(match self.skipped.last().map(|skip| skip.reason) {
+            Some(SkipReason::OtherLanguage) => {
+                "no files of the selected languages found, nothing to copy"
+            }
+            Some(SkipReason::GrepFiltered) => "no files match the --grep patterns, nothing to copy",
+            Some(SkipReason::NotRelevant) => {
+                "no files are relevant to the --rank query, nothing to copy"
+            }
+            Some(SkipReason::Unchanged) => "nothing changed since the last copy",
+            _ => "no matching files found, nothing to copy",
+        })
+    }
+}
+
+/// Narrow the collected `files` under `root` down by `selection` and the
+/// `--grep` filter, in that order: languages, imports, grep, rank and
+/// changes since the last copy.
+pub fn select_files(
+    root: &Path,
+    mut files: Vec<PathBuf>,
+    selection: &Selection,
+    grep: Option<&GrepOptions>,
+) -> Result<Selected> {
+    let mut selected = Selected::default();
+
+    if let Some(languages) = &selection.languages {
+        let (kept, dropped) = languages.split(files);
+        for (file, tag) in dropped {
+            let detail = format!("detected as {}", tag);
+            selected
+                .skipped
+                .push(Skipped::new(file, SkipReason::OtherLanguage, Some(detail)));
+        }
+        files = kept;
+        if files.is_empty() {
+            return Ok(selected);
+        }
+    }
+
+    if let Some(follow) = &selection.follow {
+        let kept = follow::follow_imports(follow, &files)
+            .map_err(|e| anyhow!("failed to follow imports: {}", e))?;
+        let detail = format!("not reached from {}", follow.entry.display());
+        files = dropped(files, kept, SkipReason::NotImported, &detail, &mut selected);
+    }
+
+    if let Some(grep) = grep {
+        let kept = grep.filter(files.clone());
+        let detail = if grep.invert {
+            "matches --grep-invert patterns"
+        } else {
+            "does not match --grep patterns"
+        };
+        files = dropped(files, kept, SkipReason::GrepFiltered, detail, &mut selected);
+        if files.is_empty() {
+            return Ok(selected);
+        }
+    }
+
+    if let Some(options) = &selection.rank {
+        let kept = rank::rank_files(root, files.clone(), options);
+        let detail = format!("for \"{}\"", options.query);
+        files = dropped(files, kept, SkipReason::NotRelevant, &detail, &mut selected);
+        if files.is_empty() {
+            return Ok(selected);
+        }
+    }
+
+    if let Some(previous) = &selection.since_last {
+        let current = Manifest::of(root, &files);
+        let kept = previous.changed(root, files.clone(), &current);
+        let detail = "since the last copy";
+        files = dropped(files, kept, SkipReason::Unchanged, detail, &mut selected);
+        selected.deleted = previous.deleted(root);
+        selected.manifest = Some(current);
+    }
+
+    selected.files = files;
+    Ok(selected)
+}
+
+/// Record the files of `before` missing from `kept` as skipped for
+/// `reason`, and return `kept`.
+fn dropped(
+    before: Vec<PathBuf>,
+    kept: Vec<PathBuf>,
+    reason: SkipReason,
+    detail: &str,
+    selected: &mut Selected,
+) -> Vec<PathBuf> {
+    let retained: HashSet<&PathBuf> = kept.iter().collect();
+    for file in before {
+        if !retained.contains(&file) {
+            let skip = Skipped::new(file, reason, Some(detail.to_string()));
+            selected.skipped.push(skip);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::fs;
+
+    fn write(root: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = root.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_select_files_records_every_step() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files = vec![
+            write(
+                root,
+                "main.rs",
+                "mod parser;\nfn main() { parser::parse(); }\n",
+            ),
+            write(root, "parser.rs", "pub fn parse() { /* tokens */ }\n"),
+            write(root, "lexer.rs", "pub fn lex() {}\n"),
+            write(root, "notes.md", "# Notes\n"),
+        ];
+        let selection = Selection {
+            languages: Some(LanguageFilter::new(&["rust".to_string()], &[]).unwrap()),
+            follow: Some(Follow {
+                entry: files[0].clone(),
+                max_depth: None,
+            }),
+            rank: Some(RankOptions {
+                query: "parse tokens".to_string(),
+                top: Some(1),
+                token_budget: None,
+            }),
+            since_last: None,
+        };
+        let grep = GrepOptions {
+            patterns: vec![Regex::new("parse").unwrap()],
+            invert: false,
+            context: None,
+        };
+
+        let selected = select_files(root, files.clone(), &selection, Some(&grep)).unwrap();
+        assert_eq!(selected.files, vec![files[1].clone()]);
+        let reasons: Vec<(&Path, SkipReason)> = selected
+            .skipped
+            .iter()
+            .map(|skip| (skip.path.strip_prefix(root).unwrap(), skip.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (Path::new("notes.md"), SkipReason::OtherLanguage),
+                (Path::new("lexer.rs"), SkipReason::NotImported),
+                (Path::new("main.rs"), SkipReason::NotRelevant),
+            ]
+        );
+        assert_eq!(
+            selected.skipped[0].detail.as_deref(),
+            Some("detected as markdown")
+        );
+        assert_eq!(selected.nothing_left(), None);
+    }
+
+    #[test]
+    fn test_select_files_stops_when_nothing_is_left() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = vec![write(dir.path(), "main.rs", "fn main() {}\n")];
+        let grep = GrepOptions {
+            patterns: vec![Regex::new("needle").unwrap()],
+            invert: false,
+            context: None,
+        };
+
+        let selected = select_files(dir.path(), files, &Selection::default(), Some(&grep)).unwrap();
+        assert!(selected.files.is_empty());
+        assert_eq!(selected.skipped[0].reason, SkipReason::GrepFiltered);
+        assert_eq!(
+            selected.nothing_left(),
+            Some("no files match the --grep patterns, nothing to copy")
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::files::SkipReason;
+use crate::manifest::Manifest;
+
+/// Rough number of characters per token used by [`estimate_tokens`].
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Estimates how many LLM tokens `text` will cost.
+///
+/// This is the usual "four characters per token" heuristic; it is meant for
+/// budgeting, not for exact accounting against a specific tokenizer.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// `count` followed by `noun`, pluralized with an `s` unless `count` is 1,
+/// e.g. `1 file` or `3 files`.
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Formats a byte count for humans, e.g. `512 B` or `1.5 KiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Size metrics for a single included file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStats {
+    /// Path relative to the collection root.
+    pub path: PathBuf,
+    /// Code block tag returned by [`crate::language::detect_language`].
+    pub language: String,
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+/// Aggregated metrics for all files of one language.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct LanguageStats {
+    pub files: usize,
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+}
+
+/// Summary of everything that went into (and was left out of) a copy.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub files: usize,
+    pub bytes: usize,
+    pub lines: usize,
+    pub tokens: usize,
+    /// The largest included files, biggest first.
+    pub largest: Vec<FileStats>,
+    /// Per-language breakdown keyed by code block tag.
+    pub languages: BTreeMap<String, LanguageStats>,
+    /// Skipped paths grouped by reason.
+    pub skipped: BTreeMap<SkipReason, Vec<PathBuf>>,
+    /// Hashes of the included files as they were read, for `--since-last`.
+    #[serde(skip)]
+    pub manifest: Manifest,
+    #[serde(skip)]
+    top: usize,
+}
+
+impl Summary {
+    /// Create an empty summary that keeps track of the `top` largest files.
+    pub fn new(top: usize) -> Self {
+        Summary {
+            manifest: Manifest::new(),
+            top,
+            ..Default::default()
+        }
+    }
+
+    /// Record an included file with the given `contents`.
+    pub fn record_file(&mut self, rel_path: &Path, language: &str, contents: &str) {
+        let stats = FileStats {
+            path: rel_path.to_path_buf(),
+            language: language.to_string(),
+            bytes: contents.len(),
+            lines: contents.lines().count(),
+            tokens: estimate_tokens(contents),
+        };
+
+        self.files += 1;
+        self.bytes += stats.bytes;
+        self.lines += stats.lines;
+        self.tokens += stats.tokens;
+
+        let lang = self.languages.entry(stats.language.clone()).or_default();
+        lang.files += 1;
+        lang.bytes += stats.bytes;
+        lang.lines += stats.lines;
+        lang.tokens += stats.tokens;
+
+        if self.top > 0 {
+            let pos = self.largest.partition_point(|f| f.bytes >= stats.bytes);
+            if pos < self.top {
+                self.largest.insert(pos, stats);
+                self.largest.truncate(self.top);
+            }
+        }
+    }
+
+    /// Record a path that was left out of the output.
+    pub fn record_skip(&mut self, rel_path: &Path, reason: SkipReason) {
+        self.skipped
+            .entry(reason)
+            .or_default()
+            .push(rel_path.to_path_buf());
+    }
+
+    /// Render the summary as JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl std::fmt::Display for Summary {
+    /// Render the summary as a short human-readable report.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{}, {}, {}, ~{}",
+            plural(self.files, "file"),
+            plural(self.lines, "line"),
+            plural(self.bytes, "byte"),
+            plural(self.tokens, "token")
+        )?;
+
+        if !self.largest.is_empty() {
+            writeln!(f, "largest files:")?;
+            for file in &self.largest {
+                writeln!(
+                    f,
+                    "  {:>10} bytes  ~{:>8} tokens  {}",
+                    file.bytes,
+                    file.tokens,
+                    file.path.display()
+                )?;
+            }
+        }
+
+        if !self.languages.is_empty() {
+            writeln!(f, "languages:")?;
+            for (tag, lang) in &self.languages {
+                writeln!(
+                    f,
+                    "  {:<12} {:>5} files {:>8} lines  ~{:>8} tokens",
+                    tag, lang.files, lang.lines, lang.tokens
+                )?;
+            }
+        }
+
+        if !self.skipped.is_empty() {
+            writeln!(f, "skipped:")?;
+            for (reason, paths) in &self.skipped {
+                let names: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                writeln!(
+                    f,
+                    "  {:<14} {:>5}  {}",
+                    reason,
+                    paths.len(),
+                    names.join(", ")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(plural(0, "file"), "0 files");
+        assert_eq!(plural(1, "file"), "1 file");
+        assert_eq!(plural(2, "line"), "2 lines");
+
+        let mut summary = Summary::new(0);
+        summary.record_file(Path::new("a.rs"), "rust", "fn a() {}\n");
+        assert!(summary
+            .to_string()
+            .starts_with("1 file, 1 line, 10 bytes, ~3 tokens\n"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_record_file_totals_and_languages() {
+        let mut summary = Summary::new(5);
+        summary.record_file(Path::new("a.rs"), "rust", "fn a() {}\n");
+        summary.record_file(Path::new("b.rs"), "rust", "fn b() {}\nfn c() {}\n");
+        summary.record_file(Path::new("c.py"), "python", "pass\n");
+
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.lines, 4);
+        assert_eq!(summary.bytes, 35);
+        assert_eq!(summary.languages["rust"].files, 2);
+        assert_eq!(summary.languages["rust"].lines, 3);
+        assert_eq!(summary.languages["python"].files, 1);
+    }
+
+    #[test]
+    fn test_largest_keeps_top_n_sorted() {
+        let mut summary = Summary::new(2);
+        summary.record_file(Path::new("small"), "text", "a");
+        summary.record_file(Path::new("large"), "text", "aaaaaaaa");
+        summary.record_file(Path::new("medium"), "text", "aaaa");
+
+        let names: Vec<&Path> = summary.largest.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(names, vec![Path::new("large"), Path::new("medium")]);
+    }
+
+    #[test]
+    fn test_skipped_grouped_by_reason() {
+        let mut summary = Summary::new(0);
+        summary.record_skip(Path::new("target"), SkipReason::Ignored);
+        summary.record_skip(Path::new("logo.png"), SkipReason::Binary);
+        summary.record_skip(Path::new("Cargo.lock"), SkipReason::Ignored);
+
+        assert_eq!(summary.skipped[&SkipReason::Ignored].len(), 2);
+        assert_eq!(summary.skipped[&SkipReason::Binary].len(), 1);
+
+        let json: serde_json::Value = serde_json::from_str(&summary.to_json().unwrap()).unwrap();
+        assert_eq!(json["skipped"]["ignored"][1], "Cargo.lock");
+        assert_eq!(json["skipped"]["binary"][0], "logo.png");
+    }
+}
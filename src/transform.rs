@@ -0,0 +1,690 @@
+/// Options for the transform stage applied to file contents before they are
+/// formatted.
+#[derive(Debug, Default, Clone)]
+pub struct TransformOptions {
+    /// Remove comments, using the lexical rules of the file's language.
+    pub strip_comments: bool,
+    /// When stripping comments, keep documentation comments.
+    pub keep_doc_comments: bool,
+    /// Strip trailing whitespace and collapse runs of blank lines.
+    pub compact_whitespace: bool,
+}
+
+impl TransformOptions {
+    /// Whether any transform is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.strip_comments || self.compact_whitespace
+    }
+}
+
+/// Lexical rules needed to find comments without touching string literals.
+struct Syntax {
+    /// Line comment openers, e.g. `//`.
+    line: &'static [&'static str],
+    /// Block comment delimiters, e.g. `/*` and `*/`.
+    block: &'static [(&'static str, &'static str)],
+    /// String delimiters, longest first so `"""` wins over `"`.
+    strings: &'static [&'static str],
+    /// Line comment openers that mark documentation.
+    doc_line: &'static [&'static str],
+    /// Block comment openers that mark documentation.
+    doc_block: &'static [&'static str],
+    /// Block comments nest (Rust, Haskell-style).
+    nested: bool,
+    /// A line comment must start a line or follow whitespace (shell `$#`).
+    line_needs_space: bool,
+    /// String delimiters only open a string at the start of a token, so
+    /// the `'` of a plain YAML scalar like `it's` is just text.
+    strings_start_token: bool,
+    /// A `/` where an expression may start opens a regex literal
+    /// (JavaScript), so `/\/\//g` is not a comment.
+    regex: bool,
+    /// Length of a string literal without backslash escapes starting a
+    /// token, e.g. a C++ raw string or a C# verbatim string.
+    raw_string: Option<fn(&str) -> Option<usize>>,
+    /// Rust raw strings (`r#"…"#`) and char literals vs. lifetimes.
+    rust: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    strings: &["\"", "'"],
+    doc_line: &[],
+    doc_block: &["/**"],
+    nested: false,
+    line_needs_space: false,
+    strings_start_token: false,
+    regex: false,
+    raw_string: None,
+    rust: false,
+};
+
+const CPP: Syntax = Syntax {
+    raw_string: Some(cpp_raw_string_len),
+    ..C_LIKE
+};
+
+const CSHARP: Syntax = Syntax {
+    raw_string: Some(csharp_verbatim_len),
+    ..C_LIKE
+};
+
+const JS_LIKE: Syntax = Syntax {
+    strings: &["\"", "'", "`"],
+    ..C_LIKE
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    regex: true,
+    ..JS_LIKE
+};
+
+const RUST: Syntax = Syntax {
+    strings: &["\""],
+    doc_line: &["///", "//!"],
+    doc_block: &["/**", "/*!"],
+    nested: true,
+    rust: true,
+    ..C_LIKE
+};
+
+const HASH: Syntax = Syntax {
+    line: &["#"],
+    block: &[],
+    strings: &["\"", "'"],
+    doc_line: &[],
+    doc_block: &[],
+    nested: false,
+    line_needs_space: true,
+    strings_start_token: false,
+    regex: false,
+    raw_string: None,
+    rust: false,
+};
+
+const YAML: Syntax = Syntax {
+    strings_start_token: true,
+    ..HASH
+};
+
+/// Makefiles and Dockerfiles hand quotes to the shell line by line; an
+/// apostrophe in a recipe or comment must not hide later comments.
+const UNQUOTED_HASH: Syntax = Syntax {
+    strings: &[],
+    ..HASH
+};
+
+const PYTHON: Syntax = Syntax {
+    strings: &["\"\"\"", "'''", "\"", "'"],
+    line_needs_space: false,
+    ..HASH
+};
+
+const SQL: Syntax = Syntax {
+    line: &["--"],
+    block: &[("/*", "*/")],
+    line_needs_space: false,
+    ..HASH
+};
+
+const LUA: Syntax = Syntax {
+    line: &["--"],
+    block: &[("--[[", "]]")],
+    line_needs_space: false,
+    ..HASH
+};
+
+const MARKUP: Syntax = Syntax {
+    line: &[],
+    block: &[("<!--", "-->")],
+    strings: &[],
+    ..HASH
+};
+
+/// The lexical rules for a code block tag, if comments can be stripped.
+fn syntax(language: &str) -> Option<&'static Syntax> {
+    Some(match language {
+        "rust" => &RUST,
+        "c" | "java" | "kotlin" | "scala" | "swift" | "d" | "objc" | "groovy" | "solidity"
+        | "php" => &C_LIKE,
+        "cpp" | "objc++" => &CPP,
+        "csharp" => &CSHARP,
+        "javascript" | "jsx" | "typescript" | "tsx" => &JAVASCRIPT,
+        "go" | "dart" => &JS_LIKE,
+        "css" => &Syntax {
+            line: &[],
+            ..C_LIKE
+        },
+        "python" => &PYTHON,
+        "shell" | "ruby" | "perl" | "r" | "powershell" | "julia" | "graphql" => &HASH,
+        "yaml" => &YAML,
+        "makefile" | "dockerfile" => &UNQUOTED_HASH,
+        "sql" => &SQL,
+        "lua" => &LUA,
+        "html" | "xml" => &MARKUP,
+        _ => return None,
+    })
+}
+
+/// Apply the enabled transforms to `source`, a file tagged `language` by
+/// [`crate::language::detect_language`].
+///
+/// Comments are only stripped for languages with known lexical rules; string
+/// literals are copied verbatim, so `"//"` or `'#'` inside them survive.
+/// Lines left empty by a removed comment are dropped entirely.
+pub fn transform(source: &str, language: &str, options: &TransformOptions) -> String {
+    let mut text = match syntax(language).filter(|_| options.strip_comments) {
+        Some(syntax) => strip_comments(source, syntax, options.keep_doc_comments),
+        None => source.to_string(),
+    };
+    if options.compact_whitespace {
+        text = compact_whitespace(&text);
+    }
+    text
+}
+
+/// Remove the comments of `source` according to `syntax`.
+fn strip_comments(source: &str, syntax: &Syntax, keep_docs: bool) -> String {
+    let mut lexer = Lexer {
+        rest: source,
+        out: String::with_capacity(source.len()),
+        line: String::new(),
+        line_had_comment: false,
+        regex_allowed: true,
+    };
+
+    // A shebang is an interpreter directive, not a comment.
+    if lexer.rest.starts_with("#!") {
+        let end = lexer.rest.find('\n').unwrap_or(lexer.rest.len());
+        lexer.keep(end);
+    }
+
+    while let Some(c) = lexer.rest.chars().next() {
+        if c == '\n' {
+            lexer.end_line();
+            continue;
+        }
+
+        // `r"…"` only opens a raw string at the start of a token, while
+        // byte chars like `b'"'` follow one.
+        let after_ident = lexer
+            .line
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        if let Some(len) = syntax
+            .raw_string
+            .filter(|_| !after_ident)
+            .and_then(|raw_string_len| raw_string_len(lexer.rest))
+        {
+            lexer.keep(len);
+            continue;
+        }
+        if let Some(open) = find_prefix(lexer.rest, syntax.strings) {
+            if !(syntax.strings_start_token && after_ident) {
+                let len = string_len(lexer.rest, open);
+                lexer.keep(len);
+                continue;
+            }
+        }
+        if syntax.regex && c == '/' && lexer.regex_allowed {
+            if let Some(len) = regex_len(lexer.rest) {
+                lexer.keep(len);
+                continue;
+            }
+        }
+        if syntax.rust && (c == '\'' || !after_ident) {
+            if let Some(len) = rust_literal_len(lexer.rest) {
+                lexer.keep(len);
+                continue;
+            }
+        }
+
+        if let Some(&(open, close)) = syntax.block.iter().find(|(o, _)| lexer.rest.starts_with(o)) {
+            let len = block_len(lexer.rest, open, close, syntax.nested);
+            if keep_docs && is_doc(lexer.rest, syntax.doc_block, close) {
+                lexer.keep(len);
+            } else {
+                lexer.drop(len);
+            }
+            continue;
+        }
+
+        let at_boundary = lexer
+            .line
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+        if find_prefix(lexer.rest, syntax.line).is_some()
+            && (at_boundary || !syntax.line_needs_space)
+        {
+            let len = lexer.rest.find('\n').unwrap_or(lexer.rest.len());
+            if keep_docs && is_doc(lexer.rest, syntax.doc_line, "") {
+                lexer.keep(len);
+            } else {
+                lexer.drop(len);
+            }
+            continue;
+        }
+
+        lexer.keep(c.len_utf8());
+    }
+    lexer.finish()
+}
+
+/// Output state of [`strip_comments`], built line by line so lines that only
+/// held a comment can be dropped.
+struct Lexer<'a> {
+    rest: &'a str,
+    out: String,
+    line: String,
+    line_had_comment: bool,
+    /// Whether the last token kept lets an expression, and so a regex
+    /// literal, follow.
+    regex_allowed: bool,
+}
+
+impl Lexer<'_> {
+    /// Copy the next `len` bytes to the output.
+    fn keep(&mut self, len: usize) {
+        let kept = &self.rest[..len];
+        self.line.push_str(kept);
+        self.rest = &self.rest[len..];
+        if !kept.trim().is_empty() {
+            self.regex_allowed = regex_may_follow(&self.line);
+        }
+    }
+
+    /// Skip the next `len` bytes.
+    fn drop(&mut self, len: usize) {
+        self.rest = &self.rest[len..];
+        self.line_had_comment = true;
+    }
+
+    /// Consume a newline and flush the current line unless a comment left
+    /// it blank; whitespace before a removed trailing comment is dropped.
+    fn end_line(&mut self) {
+        self.rest = &self.rest[1..];
+        if !self.line_had_comment {
+            self.out.push_str(&self.line);
+            self.out.push('\n');
+        } else if !self.line.trim().is_empty() {
+            self.out.push_str(self.line.trim_end_matches([' ', '\t']));
+            self.out.push('\n');
+        }
+        self.line.clear();
+        self.line_had_comment = false;
+    }
+
+    fn finish(mut self) -> String {
+        if !(self.line_had_comment && self.line.trim().is_empty()) {
+            self.out.push_str(&self.line);
+        }
+        self.out
+    }
+}
+
+/// The first of `prefixes` that `text` starts with.
+fn find_prefix<'a>(text: &str, prefixes: &[&'a str]) -> Option<&'a str> {
+    prefixes.iter().copied().find(|p| text.starts_with(p))
+}
+
+/// Whether the comment at the start of `text` is a documentation comment.
+///
+/// `////` and `/***/`-style runs are not doc comments, following rustdoc.
+fn is_doc(text: &str, openers: &[&str], close: &str) -> bool {
+    openers.iter().any(|open| {
+        let after = &text[open.len()..];
+        text.starts_with(open)
+            && !after.starts_with(&open[open.len() - 1..])
+            && (close.is_empty() || !after.starts_with(close))
+    })
+}
+
+/// Length of the string literal opened by `delim` at the start of `text`,
+/// honoring backslash escapes; unterminated strings run to the end.
+fn string_len(text: &str, delim: &str) -> usize {
+    let mut chars = text[delim.len()..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if text[delim.len() + i..].starts_with(delim) {
+            return delim.len() + i + delim.len();
+        }
+    }
+    text.len()
+}
+
+/// Keywords after which a `/` starts a regex literal rather than a division.
+const REGEX_KEYWORDS: &[&str] = &[
+    "await",
+    "case",
+    "delete",
+    "do",
+    "else",
+    "in",
+    "instanceof",
+    "new",
+    "of",
+    "return",
+    "throw",
+    "typeof",
+    "void",
+    "yield",
+];
+
+/// Whether a `/` after `line` (the current line up to the lexer position)
+/// is in expression position: after an operator, an opening bracket or a
+/// keyword like `return`, but not after a value such as an identifier,
+/// number, string or closing bracket.
+fn regex_may_follow(line: &str) -> bool {
+    let line = line.trim_end();
+    let Some(last) = line.chars().next_back() else {
+        return true;
+    };
+    if last.is_alphanumeric() || last == '_' || last == '$' {
+        let word = line
+            .rsplit(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .next()
+            .unwrap_or_default();
+        return REGEX_KEYWORDS.contains(&word);
+    }
+    !matches!(last, ')' | ']' | '"' | '\'' | '`')
+}
+
+/// Length of the regex literal at the start of `text`, including its flags,
+/// or `None` when the `/` opens a comment or the literal does not end on
+/// the same line.
+fn regex_len(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('/')?;
+    if body.starts_with(['/', '*']) {
+        return None;
+    }
+    let mut in_class = false;
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\n' => return None,
+            '\\' => match chars.next() {
+                None | Some((_, '\n')) => return None,
+                Some(_) => {}
+            },
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                let flags = body[i + 1..]
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(body.len() - i - 1);
+                return Some(1 + i + 1 + flags);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Length of the block comment opened by `open` at the start of `text`.
+fn block_len(text: &str, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if (depth == 0 || nested) && rest.starts_with(open) {
+            depth += 1;
+            i += open.len();
+        } else if rest.starts_with(close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    text.len()
+}
+
+/// Length of a C++ raw string literal such as `R"(a // b)"` or
+/// `u8R"sql(…)sql"` at the start of `text`; unterminated ones run to the end.
+fn cpp_raw_string_len(text: &str) -> Option<usize> {
+    let prefix = ["u8R\"", "uR\"", "UR\"", "LR\"", "R\""]
+        .iter()
+        .find(|p| text.starts_with(*p))?;
+    let body = &text[prefix.len()..];
+    let open = body.find('(')?;
+    let delimiter = &body[..open];
+    // Delimiters are at most 16 chars without spaces, parentheses or
+    // backslashes.
+    if delimiter.len() > 16 || delimiter.contains([' ', ')', '\\', '\n', '"']) {
+        return None;
+    }
+    let close = format!("){}\"", delimiter);
+    let start = prefix.len() + open + 1;
+    Some(
+        text[start..]
+            .find(&close)
+            .map_or(text.len(), |end| start + end + close.len()),
+    )
+}
+
+/// Length of a C# verbatim string literal such as `@"C:\dir\"` (also
+/// interpolated, `$@"…"`) at the start of `text`, where `""` is an escaped
+/// quote and backslashes are plain text.
+fn csharp_verbatim_len(text: &str) -> Option<usize> {
+    let prefix = ["@\"", "$@\"", "@$\""]
+        .iter()
+        .find(|p| text.starts_with(*p))?;
+    let mut i = prefix.len();
+    while let Some(end) = text[i..].find('"') {
+        i += end + 1;
+        if !text[i..].starts_with('"') {
+            return Some(i);
+        }
+        i += 1;
+    }
+    Some(text.len())
+}
+
+/// Length of a Rust raw string or char literal at the start of `text`.
+///
+/// Lifetimes such as `'a` are not literals and return `None`, as does
+/// anything that is not a raw string or char literal.
+fn rust_literal_len(text: &str) -> Option<usize> {
+    if let Some(after_r) = text.strip_prefix("br").or_else(|| text.strip_prefix('r')) {
+        let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+        if after_r[hashes..].starts_with('"') {
+            let prefix = text.len() - after_r.len() + hashes + 1;
+            let close = format!("\"{}", "#".repeat(hashes));
+            return Some(
+                text[prefix..]
+                    .find(&close)
+                    .map_or(text.len(), |end| prefix + end + close.len()),
+            );
+        }
+        return None;
+    }
+
+    let body = text.strip_prefix('\'')?;
+    let mut chars = body.char_indices();
+    match chars.next()? {
+        (_, '\\') => {
+            // Skip the escaped char, then find the closing quote.
+            let skip = 1 + body[1..].chars().next()?.len_utf8();
+            body[skip..].find('\'').map(|end| 1 + skip + end + 1)
+        }
+        (_, c) => match chars.next() {
+            Some((i, '\'')) if c != '\'' => Some(1 + i + 1),
+            _ => None,
+        },
+    }
+}
+
+/// Strip trailing whitespace, collapse runs of blank lines into one and drop
+/// leading and trailing blank lines.
+fn compact_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank_run = false;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run = !out.is_empty();
+            continue;
+        }
+        if blank_run {
+            out.push('\n');
+            blank_run = false;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(source: &str, language: &str, keep_docs: bool) -> String {
+        let options = TransformOptions {
+            strip_comments: true,
+            keep_doc_comments: keep_docs,
+            compact_whitespace: false,
+        };
+        transform(source, language, &options)
+    }
+
+    #[test]
+    fn test_strip_rust_comments() {
+        let source = r##"// Copyright (c) Example
+// SPDX-License-Identifier: MIT
+
+/// Adds.
+fn add(a: i32, b: i32) -> i32 {
+    /* nested /* block */ comment */
+    let url = "http://example.com"; // trailing
+    let raw = r#"// not a comment"#;
+    let quote = '"'; let slash = '/'; let byte = b'"';
+    a + b
+}
+
+fn longest<'a>(x: &'a str) -> &'a str { x } // lifetimes
+"##;
+        let expected = r##"
+fn add(a: i32, b: i32) -> i32 {
+    let url = "http://example.com";
+    let raw = r#"// not a comment"#;
+    let quote = '"'; let slash = '/'; let byte = b'"';
+    a + b
+}
+
+fn longest<'a>(x: &'a str) -> &'a str { x }
+"##;
+        assert_eq!(strip(source, "rust", false), expected);
+
+        let kept = strip(source, "rust", true);
+        assert!(kept.contains("/// Adds.\nfn add"));
+        assert!(!kept.contains("Copyright"));
+    }
+
+    #[test]
+    fn test_strip_python_comments() {
+        let source = "#!/usr/bin/env python\n# comment\ndef f():\n    \"\"\"Doc # kept.\"\"\"\n    return '#' + \"#\"  # trailing\n";
+        let expected =
+            "#!/usr/bin/env python\ndef f():\n    \"\"\"Doc # kept.\"\"\"\n    return '#' + \"#\"\n";
+        assert_eq!(strip(source, "python", false), expected);
+    }
+
+    #[test]
+    fn test_strip_shell_needs_space() {
+        let source = "echo $# ${#arr}  # count\n";
+        assert_eq!(strip(source, "shell", false), "echo $# ${#arr}\n");
+    }
+
+    #[test]
+    fn test_strip_js_doc_comments() {
+        let source = "/** Docs. */\nconst s = `// ${x}`; /* gone */\n/***/\n";
+        assert_eq!(
+            strip(source, "javascript", true),
+            "/** Docs. */\nconst s = `// ${x}`;\n"
+        );
+        assert_eq!(strip(source, "javascript", false), "const s = `// ${x}`;\n");
+    }
+
+    #[test]
+    fn test_strip_js_regex_literals() {
+        let source = "const re = /\\/\\//g; // comment\n\
+                      if (/https?:\\/\\//.test(url)) return a / b; // c\n\
+                      const r = [/[/]/, x]; /* d */\n\
+                      let half = (a) / 2 / b; // e\n\
+                      return /a\\/b/i.test(s); // f\n";
+        assert_eq!(
+            strip(source, "javascript", false),
+            "const re = /\\/\\//g;\n\
+             if (/https?:\\/\\//.test(url)) return a / b;\n\
+             const r = [/[/]/, x];\n\
+             let half = (a) / 2 / b;\n\
+             return /a\\/b/i.test(s);\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_cpp_raw_strings() {
+        let source = "auto re = R\"(a // \")\"; // one\n\
+                      auto sql = u8R\"q(\")\" // )q\"; /* two */\n\
+                      auto url = \"http://x\"; // three\n";
+        assert_eq!(
+            strip(source, "cpp", false),
+            "auto re = R\"(a // \")\";\n\
+             auto sql = u8R\"q(\")\" // )q\";\n\
+             auto url = \"http://x\";\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_csharp_verbatim_strings() {
+        let source = "var dir = @\"C:\\dir\\\"; // one\n\
+                      var quote = $@\"say \"\"{x}\"\" // not a comment\"; // two\n\
+                      var url = \"http://x\"; // three\n";
+        assert_eq!(
+            strip(source, "csharp", false),
+            "var dir = @\"C:\\dir\\\";\n\
+             var quote = $@\"say \"\"{x}\"\" // not a comment\";\n\
+             var url = \"http://x\";\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_hash_quotes_per_language() {
+        let yaml = "# don't\nkey: value # note\nother: it's # gone\nquoted: 'a # b' # c\n";
+        assert_eq!(
+            strip(yaml, "yaml", false),
+            "key: value\nother: it's\nquoted: 'a # b'\n"
+        );
+        let makefile = "all:\n\techo it's # one\n\techo hi # two\n";
+        assert_eq!(
+            strip(makefile, "makefile", false),
+            "all:\n\techo it's\n\techo hi\n"
+        );
+    }
+
+    #[test]
+    fn test_unknown_language_is_untouched() {
+        let source = "# heading\n// text\n";
+        assert_eq!(strip(source, "text", false), source);
+    }
+
+    #[test]
+    fn test_compact_whitespace() {
+        let options = TransformOptions {
+            compact_whitespace: true,
+            ..Default::default()
+        };
+        let source = "\n\nfn a() {}   \n\n\n\nfn b() {}\t\n\n";
+        assert_eq!(
+            transform(source, "rust", &options),
+            "fn a() {}\n\nfn b() {}\n"
+        );
+    }
+}
@@ -1,121 +1,654 @@
-use crate::files::collect_files;
+use crate::files::{
+    collect_dirs, collect_files, collect_skipped, read_text, SkipReason, WalkOptions,
+};
+use crate::language::{detect_language, detect_language_of, LanguageFilter};
+use crate::stats::{estimate_tokens, format_bytes, plural};
 use anyhow::{anyhow, Result};
-use std::{ffi::OsStr, path::PathBuf};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::ops::AddAssign;
+use std::path::PathBuf;
+
+/// Output format of the rendered tree.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TreeFormat {
+    /// Box-drawing tree like the `tree` command.
+    #[default]
+    Ascii,
+    /// Indented list using only ASCII characters.
+    Plain,
+    /// Nested Markdown bullet list.
+    Markdown,
+    /// JSON object tree.
+    Json,
+    /// Mermaid `graph` diagram.
+    Mermaid,
+}
+
+/// Options controlling how the directory tree is rendered.
+#[derive(Debug, Default, Clone)]
+pub struct TreeOptions {
+    /// Output format.
+    pub format: TreeFormat,
+    /// Show size, line count, token estimate and language next to each
+    /// entry, with directories showing the totals of their contents.
+    pub annotate: bool,
+    /// Render only entries up to this depth; the root's children are depth 1.
+    pub depth: Option<usize>,
+    /// Render directories only, leaving out files.
+    pub dirs_only: bool,
+    /// Collapse directories containing more than this many files (at any
+    /// depth) into a single `… (N files, M dirs)` line, leaving out `0 dirs`.
+    pub collapse: Option<usize>,
+    /// Include directories that contain no collected files.
+    pub empty_dirs: bool,
+    /// Show ignored and excluded entries as marked nodes, e.g.
+    /// `target/ [ignored]`, without descending into them.
+    pub show_skipped: bool,
+    /// Include only files of the selected languages.
+    pub languages: Option<LanguageFilter>,
+}
+
+/// Size metrics of a file, or the totals of a directory's contents.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Metrics {
+    bytes: u64,
+    lines: usize,
+    tokens: usize,
+}
+
+impl AddAssign for Metrics {
+    fn add_assign(&mut self, other: Metrics) {
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+        self.tokens += other.tokens;
+    }
+}
+
+impl Metrics {
+    /// Measure the file at `path` and detect its language from its
+    /// contents; binary or unreadable files only report their size.
+    fn measure(path: &std::path::Path) -> (Metrics, String) {
+        match read_text(path, None) {
+            Ok(contents) => (
+                Metrics {
+                    bytes: contents.len() as u64,
+                    lines: contents.lines().count(),
+                    tokens: estimate_tokens(&contents),
+                },
+                detect_language_of(path, &contents),
+            ),
+            Err(_) => (
+                Metrics {
+                    bytes: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                    ..Default::default()
+                },
+                detect_language(path).to_string(),
+            ),
+        }
+    }
+
+    /// Render inline, e.g. `1.2 KiB, 30 lines, ~300 tokens`.
+    fn summary(&self) -> String {
+        format!(
+            "{}, {}, ~{}",
+            format_bytes(self.bytes),
+            plural(self.lines, "line"),
+            plural(self.tokens, "token")
+        )
+    }
+
+    /// Render as right-aligned columns.
+    fn columns(&self) -> String {
+        format!(
+            "{:>10}  {:>7} lines  ~{:>7} tokens",
+            format_bytes(self.bytes),
+            self.lines,
+            self.tokens
+        )
+    }
+}
+
+/// An entry drawn below a directory line.
+enum Entry<'a> {
+    Node(&'a TreeNode),
+    /// Stands in for the contents of a collapsed directory.
+    Collapsed {
+        files: usize,
+        dirs: usize,
+    },
+}
+
+impl Entry<'_> {
+    /// The text drawn after the branch pointer.
+    fn label(&self) -> String {
+        match self {
+            Entry::Node(node) => node.label(),
+            Entry::Collapsed { files, dirs: 0 } => format!("… ({})", plural(*files, "file")),
+            Entry::Collapsed { files, dirs } => {
+                format!("… ({}, {})", plural(*files, "file"), plural(*dirs, "dir"))
+            }
+        }
+    }
+}
 
 /// A node in the in-memory directory tree.
+///
+/// Children are keyed by their raw file name, so lookups while inserting are
+/// logarithmic, iteration is always in name order and non-UTF-8 names keep
+/// their original bytes until they are rendered.
 struct TreeNode {
-    name: String,
-    children: Vec<TreeNode>,
+    name: OsString,
+    is_dir: bool,
+    children: BTreeMap<OsString, TreeNode>,
+    /// Metrics of this file, or totals of everything below this directory.
+    metrics: Metrics,
+    /// Code block tag of a file; `None` for directories.
+    language: Option<String>,
+    /// Set for ignored or excluded entries shown as placeholders.
+    skipped: Option<SkipReason>,
 }
 
 impl TreeNode {
     /// Create a new node with the given name.
-    pub fn new(name: String) -> Self {
+    pub fn new(name: OsString) -> Self {
         TreeNode {
             name,
-            children: Vec::new(),
+            is_dir: false,
+            children: BTreeMap::new(),
+            metrics: Metrics::default(),
+            language: None,
+            skipped: None,
         }
     }
 
-    /// Insert a sequence of path components into this node.
-    pub fn insert(&mut self, comps: &[&OsStr]) {
+    /// Insert a sequence of path components into this node, adding
+    /// `metrics` to every node along the way, and return the leaf node.
+    pub fn insert(&mut self, comps: &[&OsStr], metrics: Metrics) -> &mut TreeNode {
+        self.metrics += metrics;
         if comps.is_empty() {
-            return;
-        }
-        let part = comps[0].to_string_lossy().into_owned();
-        // Find existing child or create a new one
-        let child = match self.children.iter_mut().find(|c| c.name == part) {
-            Some(c) => c,
-            None => {
-                self.children.push(TreeNode::new(part.clone()));
-                self.children.last_mut().unwrap()
-            }
-        };
+            return self;
+        }
+        self.is_dir = true;
+        let part = comps[0];
+        // Only allocate a key when the child does not exist yet
+        if !self.children.contains_key(part) {
+            self.children
+                .insert(part.to_os_string(), TreeNode::new(part.to_os_string()));
+        }
         // Recurse on the remaining components
-        child.insert(&comps[1..]);
+        self.children
+            .get_mut(part)
+            .expect("child was just inserted")
+            .insert(&comps[1..], metrics)
+    }
+
+    /// The name for display; invalid UTF-8 is replaced.
+    fn display_name(&self) -> Cow<'_, str> {
+        self.name.to_string_lossy()
+    }
+
+    /// The text drawn for this node: its name, plus a trailing `/` and the
+    /// reason for skipped placeholders.
+    fn label(&self) -> String {
+        match self.skipped {
+            Some(reason) if self.is_dir => format!("{}/ [{}]", self.display_name(), reason),
+            Some(reason) => format!("{} [{}]", self.display_name(), reason),
+            None => self.display_name().into_owned(),
+        }
     }
 
-    /// Recursively sort this node’s children by name.
-    pub fn sort(&mut self) {
-        self.children.sort_by(|a, b| a.name.cmp(&b.name));
-        for child in &mut self.children {
-            child.sort();
+    /// Number of files and directories anywhere below this node, not
+    /// counting skipped placeholders.
+    fn counts(&self) -> (usize, usize) {
+        self.children.values().fold((0, 0), |(files, dirs), child| {
+            let (f, d) = child.counts();
+            if child.skipped.is_some() {
+                (files, dirs)
+            } else if child.is_dir {
+                (files + f, dirs + d + 1)
+            } else {
+                (files + f + 1, dirs + d)
+            }
+        })
+    }
+
+    /// The entries drawn below this node when it sits at `depth`, after
+    /// applying the depth limit, the directories-only filter and collapsing.
+    fn entries(&self, depth: usize, options: &TreeOptions) -> Vec<Entry<'_>> {
+        if options.depth.is_some_and(|max| depth >= max) {
+            return Vec::new();
+        }
+        if depth > 0 {
+            let (files, dirs) = self.counts();
+            if options.collapse.is_some_and(|max| files > max) {
+                return vec![Entry::Collapsed { files, dirs }];
+            }
         }
+        self.children
+            .values()
+            .filter(|child| child.is_dir || !options.dirs_only)
+            .map(Entry::Node)
+            .collect()
     }
+}
+
+/// A rendered line of a line-based tree format, with the node it
+/// describes so metric columns can be appended once all lines are known.
+struct Line<'a> {
+    text: String,
+    node: Option<&'a TreeNode>,
+}
+
+/// Join `lines`, padding them to a common width and appending metric
+/// columns for their nodes when `annotate` is set.
+fn join_lines(lines: Vec<Line<'_>>, annotate: bool) -> String {
+    let width = lines
+        .iter()
+        .map(|line| line.text.chars().count())
+        .max()
+        .unwrap_or(0);
 
-    /// Render this node and its subtree as an ASCII tree.
+    let mut out = String::new();
+    for line in lines {
+        match line.node.filter(|node| annotate && node.skipped.is_none()) {
+            Some(node) => {
+                let pad = width - line.text.chars().count();
+                let annotated = format!(
+                    "{}{}  {}  {}",
+                    line.text,
+                    " ".repeat(pad),
+                    node.metrics.columns(),
+                    node.language.as_deref().unwrap_or("")
+                );
+                out.push_str(annotated.trim_end());
+            }
+            None => out.push_str(&line.text),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders a [`TreeNode`] in one output format, honoring the view options
+/// (depth, directories only, collapsing) through [`TreeNode::entries`].
+trait Render {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String;
+}
+
+/// Pick the renderer for `format`.
+fn renderer(format: TreeFormat) -> Box<dyn Render> {
+    match format {
+        TreeFormat::Ascii => Box::new(AsciiRenderer),
+        TreeFormat::Plain => Box::new(PlainRenderer),
+        TreeFormat::Markdown => Box::new(MarkdownRenderer),
+        TreeFormat::Json => Box::new(JsonRenderer),
+        TreeFormat::Mermaid => Box::new(MermaidRenderer),
+    }
+}
+
+/// The classic `tree`-style rendering with box-drawing characters.
+struct AsciiRenderer;
+
+impl AsciiRenderer {
+    /// Render the entries below `node`, each prefixed with `prefix`.
     ///
-    /// `prefix` is the accumulated indent, `is_last` whether this node
-    /// is the last child at its level (to pick └ vs ├).
-    pub fn fmt(&self, prefix: &str, is_last: bool) -> String {
-        // choose the branch pointer
-        let pointer = if is_last { "└── " } else { "├── " };
-
-        // this node’s own line
-        let mut out = format!("{}{}{}\n", prefix, pointer, self.name);
-
-        // prepare prefix for children
-        let new_prefix = if is_last {
-            format!("{}    ", prefix) // pad spaces under a └──
+    /// `depth` is the depth of `node` below the root; each entry picks
+    /// └ vs ├ depending on whether it is the last one at its level.
+    fn entries<'a>(
+        node: &'a TreeNode,
+        prefix: &str,
+        depth: usize,
+        options: &TreeOptions,
+        lines: &mut Vec<Line<'a>>,
+    ) {
+        let entries = node.entries(depth, options);
+        let last_idx = entries.len().saturating_sub(1);
+        for (i, entry) in entries.iter().enumerate() {
+            let is_last = i == last_idx;
+            // choose the branch pointer
+            let pointer = if is_last { "└── " } else { "├── " };
+            let text = format!("{}{}{}", prefix, pointer, entry.label());
+
+            match entry {
+                Entry::Node(child) => {
+                    lines.push(Line {
+                        text,
+                        node: Some(child),
+                    });
+                    // prepare prefix for children
+                    let new_prefix = if is_last {
+                        format!("{}    ", prefix) // pad spaces under a └──
+                    } else {
+                        format!("{}│   ", prefix) // keep the │ going
+                    };
+                    Self::entries(child, &new_prefix, depth + 1, options, lines);
+                }
+                Entry::Collapsed { .. } => lines.push(Line { text, node: None }),
+            }
+        }
+    }
+}
+
+impl Render for AsciiRenderer {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String {
+        let mut lines = vec![Line {
+            text: root.display_name().into_owned(),
+            node: Some(root),
+        }];
+        Self::entries(root, "", 0, options, &mut lines);
+        join_lines(lines, options.annotate)
+    }
+}
+
+/// A plain indented list using only ASCII characters, directories marked
+/// with a trailing `/`.
+struct PlainRenderer;
+
+impl PlainRenderer {
+    fn node<'a>(
+        node: &'a TreeNode,
+        depth: usize,
+        options: &TreeOptions,
+        lines: &mut Vec<Line<'a>>,
+    ) {
+        let indent = "  ".repeat(depth);
+        let slash = if node.is_dir && node.skipped.is_none() {
+            "/"
+        } else {
+            ""
+        };
+        lines.push(Line {
+            text: format!("{}{}{}", indent, node.label(), slash),
+            node: Some(node),
+        });
+        for entry in node.entries(depth, options) {
+            match entry {
+                Entry::Node(child) => Self::node(child, depth + 1, options, lines),
+                collapsed => lines.push(Line {
+                    text: format!("{}  {}", indent, collapsed.label().replace('…', "...")),
+                    node: None,
+                }),
+            }
+        }
+    }
+}
+
+impl Render for PlainRenderer {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String {
+        let mut lines = Vec::new();
+        Self::node(root, 0, options, &mut lines);
+        join_lines(lines, options.annotate)
+    }
+}
+
+/// A nested Markdown bullet list with names in code spans.
+struct MarkdownRenderer;
+
+impl MarkdownRenderer {
+    fn node(node: &TreeNode, depth: usize, options: &TreeOptions, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        let name = if node.is_dir {
+            format!("{}/", node.display_name())
         } else {
-            format!("{}│   ", prefix) // keep the │ going
+            node.display_name().into_owned()
         };
+        out.push_str(&format!("{}- `{}`", indent, name));
+        if let Some(reason) = node.skipped {
+            out.push_str(&format!(" *({})*", reason));
+        } else if options.annotate {
+            out.push_str(&format!(" — {}", node.metrics.summary()));
+            if let Some(language) = &node.language {
+                out.push_str(&format!(", {}", language));
+            }
+        }
+        out.push('\n');
+
+        for entry in node.entries(depth, options) {
+            match entry {
+                Entry::Node(child) => Self::node(child, depth + 1, options, out),
+                collapsed => out.push_str(&format!("{}  - {}\n", indent, collapsed.label())),
+            }
+        }
+    }
+}
+
+impl Render for MarkdownRenderer {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String {
+        let mut out = String::new();
+        Self::node(root, 0, options, &mut out);
+        out
+    }
+}
+
+/// A JSON object tree; metrics are included when annotating.
+struct JsonRenderer;
+
+/// Serialized form of a tree entry.
+#[derive(Serialize)]
+struct JsonNode<'a> {
+    name: Option<Cow<'a, str>>,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<SkipReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dirs: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<JsonNode<'a>>>,
+}
+
+impl<'a> JsonNode<'a> {
+    fn new(node: &'a TreeNode, depth: usize, options: &TreeOptions) -> Self {
+        let annotate = options.annotate && node.skipped.is_none();
+        let children = (node.is_dir && node.skipped.is_none()).then(|| {
+            node.entries(depth, options)
+                .into_iter()
+                .map(|entry| match entry {
+                    Entry::Node(child) => JsonNode::new(child, depth + 1, options),
+                    Entry::Collapsed { files, dirs } => JsonNode::collapsed(files, dirs),
+                })
+                .collect()
+        });
+
+        JsonNode {
+            name: Some(node.display_name()),
+            kind: if node.is_dir { "directory" } else { "file" },
+            skipped: node.skipped,
+            bytes: annotate.then_some(node.metrics.bytes),
+            lines: annotate.then_some(node.metrics.lines),
+            tokens: annotate.then_some(node.metrics.tokens),
+            language: node.language.as_deref().filter(|_| annotate),
+            files: None,
+            dirs: None,
+            children,
+        }
+    }
+
+    fn collapsed(files: usize, dirs: usize) -> Self {
+        JsonNode {
+            name: None,
+            kind: "collapsed",
+            skipped: None,
+            bytes: None,
+            lines: None,
+            tokens: None,
+            language: None,
+            files: Some(files),
+            dirs: Some(dirs),
+            children: None,
+        }
+    }
+}
+
+impl Render for JsonRenderer {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String {
+        let tree = JsonNode::new(root, 0, options);
+        let mut out = serde_json::to_string_pretty(&tree).expect("tree serializes to JSON");
+        out.push('\n');
+        out
+    }
+}
+
+/// A Mermaid `graph` diagram with one node per entry.
+struct MermaidRenderer;
 
-        // render each child
-        let last_idx = self.children.len().saturating_sub(1);
-        for (i, child) in self.children.iter().enumerate() {
-            let last = i == last_idx;
-            out.push_str(&child.fmt(&new_prefix, last));
+impl MermaidRenderer {
+    /// Append the edges from node `id` to its entries, numbering new nodes
+    /// from `next`.
+    fn edges(
+        node: &TreeNode,
+        id: usize,
+        depth: usize,
+        options: &TreeOptions,
+        next: &mut usize,
+        out: &mut String,
+    ) {
+        for entry in node.entries(depth, options) {
+            let child_id = *next;
+            *next += 1;
+            let label = match &entry {
+                Entry::Node(child) => Self::label(child, options),
+                collapsed => collapsed.label(),
+            };
+            out.push_str(&format!(
+                "    n{} --> n{}[\"{}\"]\n",
+                id,
+                child_id,
+                Self::escape(&label)
+            ));
+            if let Entry::Node(child) = entry {
+                Self::edges(child, child_id, depth + 1, options, next, out);
+            }
         }
+    }
 
+    fn label(node: &TreeNode, options: &TreeOptions) -> String {
+        let mut label = node.label();
+        if node.is_dir && node.skipped.is_none() {
+            label.push('/');
+        }
+        if options.annotate && node.skipped.is_none() {
+            label.push_str(&format!("<br/>{}", node.metrics.summary()));
+        }
+        label
+    }
+
+    /// Escape characters that would end a quoted Mermaid label.
+    fn escape(label: &str) -> String {
+        label.replace('"', "#quot;")
+    }
+}
+
+impl Render for MermaidRenderer {
+    fn render(&self, root: &TreeNode, options: &TreeOptions) -> String {
+        let mut out = String::from("graph TD\n");
+        out.push_str(&format!(
+            "    n0[\"{}\"]\n",
+            Self::escape(&Self::label(root, options))
+        ));
+        let mut next = 1;
+        Self::edges(root, 0, 0, options, &mut next, &mut out);
         out
     }
 }
 
 /// Build a directory-tree string of `path`, honoring ignores & excludes.
-pub fn collect_tree(path: PathBuf, excludes: &[String], sort: bool) -> Result<String> {
-    let files = collect_files(path.clone(), excludes, sort)
+///
+/// Entries are always listed in name order.
+pub fn collect_tree(
+    path: PathBuf,
+    excludes: &[String],
+    walk: &WalkOptions,
+    options: &TreeOptions,
+) -> Result<String> {
+    let collected = collect_files(path.clone(), excludes, false, walk)
         .map_err(|e| anyhow!("failed to collect files: {}", e))?;
+    // Files of other languages are left out entirely, not shown as skipped.
+    let files = match &options.languages {
+        Some(languages) => languages.filter(collected.clone()),
+        None => collected.clone(),
+    };
 
     let root_name = path
         .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or(".")
-        .to_string();
-    let mut root = TreeNode::new(root_name.clone());
+        .map_or_else(|| OsString::from("."), OsStr::to_os_string);
+    let mut root = TreeNode::new(root_name);
 
-    for file in files {
-        if let Ok(rel) = file.strip_prefix(&path) {
-            let comps: Vec<&OsStr> = rel.components().map(|c| c.as_os_str()).collect();
-            root.insert(&comps);
+    if options.empty_dirs {
+        let dirs = collect_dirs(&path, excludes, walk)
+            .map_err(|e| anyhow!("failed to collect directories: {}", e))?;
+        for dir in dirs {
+            if let Ok(rel) = dir.strip_prefix(&path) {
+                let comps: Vec<&OsStr> = rel.components().map(|c| c.as_os_str()).collect();
+                root.insert(&comps, Metrics::default()).is_dir = true;
+            }
         }
     }
 
-    if sort {
-        root.sort();
+    for file in &files {
+        if let Ok(rel) = file.strip_prefix(&path) {
+            let comps: Vec<&OsStr> = rel.components().map(|c| c.as_os_str()).collect();
+            if options.annotate {
+                let (metrics, language) = Metrics::measure(file);
+                root.insert(&comps, metrics).language = Some(language);
+            } else {
+                root.insert(&comps, Metrics::default());
+            }
+        }
     }
 
-    let mut output = format!("{}\n", root_name);
-    let last_idx = root.children.len().saturating_sub(1);
-    for (i, child) in root.children.into_iter().enumerate() {
-        let last = i == last_idx;
-        output.push_str(&child.fmt("", last));
+    if options.show_skipped {
+        let skipped = collect_skipped(&path, excludes, &collected, walk)
+            .map_err(|e| anyhow!("failed to collect skipped files: {}", e))?;
+        for skip in skipped {
+            if let Ok(rel) = skip.path.strip_prefix(&path) {
+                let comps: Vec<&OsStr> = rel.components().map(|c| c.as_os_str()).collect();
+                let node = root.insert(&comps, Metrics::default());
+                node.is_dir = skip.path.is_dir();
+                node.skipped = Some(skip.reason);
+            }
+        }
     }
 
-    Ok(output)
+    root.is_dir = path.is_dir();
+    Ok(renderer(options.format).render(&root, options))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::collect_tree;
+    use super::{collect_tree, Metrics, Render, TreeFormat, TreeNode, TreeOptions};
+    use crate::files::WalkOptions;
+    use crate::language::LanguageFilter;
     use std::fs::{self, File};
+    use std::io::Write;
     use tempfile::tempdir;
 
     #[test]
     fn test_empty_dir() {
         let dir = tempdir().unwrap();
         let root = dir.path().to_path_buf();
-        let tree = collect_tree(root.clone(), &[], true).unwrap();
+        let tree = collect_tree(
+            root.clone(),
+            &[],
+            &WalkOptions::default(),
+            &TreeOptions::default(),
+        )
+        .unwrap();
         let root_name = root.file_name().unwrap().to_str().unwrap();
         assert_eq!(tree, format!("{}\n", root_name));
     }
@@ -127,7 +660,13 @@ mod tests {
         let file = root.join("foo.txt");
         File::create(&file).unwrap();
 
-        let tree = collect_tree(root.clone(), &[], true).unwrap();
+        let tree = collect_tree(
+            root.clone(),
+            &[],
+            &WalkOptions::default(),
+            &TreeOptions::default(),
+        )
+        .unwrap();
         let root_name = root.file_name().unwrap().to_str().unwrap();
         let expected = format!("{}\n└── foo.txt\n", root_name);
         assert_eq!(tree, expected);
@@ -143,7 +682,13 @@ mod tests {
         File::create(root.join("a/b/file2.rs")).unwrap();
         File::create(root.join("a/file1.rs")).unwrap();
 
-        let tree = collect_tree(root.clone(), &[], true).unwrap();
+        let tree = collect_tree(
+            root.clone(),
+            &[],
+            &WalkOptions::default(),
+            &TreeOptions::default(),
+        )
+        .unwrap();
         let root_name = root.file_name().unwrap().to_str().unwrap();
 
         let expected = format!(
@@ -158,6 +703,140 @@ mod tests {
         assert_eq!(tree, expected);
     }
 
+    /// Render the `a/b/file2.rs`, `a/file1.rs` sample in `format`.
+    fn render_nested(format: TreeFormat) -> (String, String) {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        File::create(root.join("a/b/file2.rs")).unwrap();
+        File::create(root.join("a/file1.rs")).unwrap();
+
+        let options = TreeOptions {
+            format,
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap().to_string();
+        (tree, root_name)
+    }
+
+    #[test]
+    fn test_nested_dirs_plain() {
+        let (tree, root) = render_nested(TreeFormat::Plain);
+        let expected = format!("{root}/\n  a/\n    b/\n      file2.rs\n    file1.rs\n");
+        assert_eq!(tree, expected);
+        assert!(tree.is_ascii());
+    }
+
+    #[test]
+    fn test_language_filter() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("docs/guide.md"), "# Guide\n").unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\n").unwrap();
+
+        let options = TreeOptions {
+            format: TreeFormat::Plain,
+            languages: Some(LanguageFilter::new(&[], &["markdown".to_string()]).unwrap()),
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let name = root.file_name().unwrap().to_str().unwrap();
+        assert_eq!(
+            tree,
+            format!("{name}/\n  Cargo.toml\n  src/\n    main.rs\n")
+        );
+
+        fs::write(root.join("data.json"), "{}\n").unwrap();
+        let options = TreeOptions {
+            format: TreeFormat::Plain,
+            show_skipped: true,
+            languages: Some(LanguageFilter::new(&["rust".to_string()], &[]).unwrap()),
+            ..Default::default()
+        };
+        let tree = collect_tree(
+            root.clone(),
+            &["Cargo.toml".to_string()],
+            &WalkOptions::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            tree,
+            format!("{name}/\n  Cargo.toml [excluded]\n  src/\n    main.rs\n")
+        );
+    }
+
+    #[test]
+    fn test_nested_dirs_markdown() {
+        let (tree, root) = render_nested(TreeFormat::Markdown);
+        let expected =
+            format!("- `{root}/`\n  - `a/`\n    - `b/`\n      - `file2.rs`\n    - `file1.rs`\n");
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_nested_dirs_json() {
+        let (tree, root) = render_nested(TreeFormat::Json);
+        let json: serde_json::Value = serde_json::from_str(&tree).unwrap();
+        let expected = serde_json::json!({
+            "name": root,
+            "type": "directory",
+            "children": [{
+                "name": "a",
+                "type": "directory",
+                "children": [
+                    {
+                        "name": "b",
+                        "type": "directory",
+                        "children": [{ "name": "file2.rs", "type": "file" }]
+                    },
+                    { "name": "file1.rs", "type": "file" }
+                ]
+            }]
+        });
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_nested_dirs_mermaid() {
+        let (tree, root) = render_nested(TreeFormat::Mermaid);
+        let expected = format!(
+            "graph TD\n    n0[\"{root}/\"]\n    n0 --> n1[\"a/\"]\n    n1 --> n2[\"b/\"]\n    n2 --> n3[\"file2.rs\"]\n    n1 --> n4[\"file1.rs\"]\n"
+        );
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_renderers_honor_collapse_and_annotations() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        create_sample_tree(&root);
+        fs::write(root.join("top.rs"), "fn main() {}\n").unwrap();
+
+        let options = TreeOptions {
+            format: TreeFormat::Json,
+            annotate: true,
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&tree).unwrap();
+        assert!(json["bytes"].as_u64().unwrap() > 0);
+        assert!(json["tokens"].as_u64().unwrap() > 0);
+
+        let options = TreeOptions {
+            format: TreeFormat::Plain,
+            collapse: Some(1),
+            ..Default::default()
+        };
+        let tree = collect_tree(root, &[], &WalkOptions::default(), &options).unwrap();
+        assert!(tree.is_ascii());
+        assert!(tree.contains("... ("));
+    }
+
     #[test]
     fn test_excludes() {
         let dir = tempdir().unwrap();
@@ -166,10 +845,255 @@ mod tests {
         File::create(root.join("keep.rs")).unwrap();
         File::create(root.join("ignore.rs")).unwrap();
 
-        let tree = collect_tree(root.clone(), &["ignore.rs".into()], true).unwrap();
+        let tree = collect_tree(
+            root.clone(),
+            &["ignore.rs".into()],
+            &WalkOptions::default(),
+            &TreeOptions::default(),
+        )
+        .unwrap();
         let root_name = root.file_name().unwrap().to_str().unwrap();
 
         let expected = format!("{r}\n└── keep.rs\n", r = root_name);
         assert_eq!(tree, expected);
     }
+
+    #[test]
+    fn test_respects_copycatignore() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("fixtures")).unwrap();
+        File::create(root.join("fixtures/big.json")).unwrap();
+        File::create(root.join("keep.rs")).unwrap();
+
+        let mut copycatignore = File::create(root.join(".copycatignore")).unwrap();
+        writeln!(copycatignore, "fixtures/").unwrap();
+
+        let tree = collect_tree(
+            root.clone(),
+            &[],
+            &WalkOptions::default(),
+            &TreeOptions::default(),
+        )
+        .unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+
+        let expected = format!("{r}\n└── keep.rs\n", r = root_name);
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_annotated_tree() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("notes.txt"), "hello\n").unwrap();
+
+        let options = TreeOptions {
+            annotate: true,
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+        let w = root_name
+            .chars()
+            .count()
+            .max("    └── main.rs".chars().count());
+
+        let expected = format!(
+            "{root:<w$}        39 B        4 lines  ~     11 tokens\n\
+{notes:<w$}         6 B        1 lines  ~      2 tokens  text\n\
+{src:<w$}        33 B        3 lines  ~      9 tokens\n\
+{lib:<w$}        20 B        2 lines  ~      5 tokens  rust\n\
+{main:<w$}        13 B        1 lines  ~      4 tokens  rust\n",
+            root = root_name,
+            notes = "├── notes.txt",
+            src = "└── src",
+            lib = "    ├── lib.rs",
+            main = "    └── main.rs",
+            w = w
+        );
+        assert_eq!(tree, expected);
+    }
+
+    /// Creates `root/a/b/file2.rs`, `root/a/file1.rs`, `root/c/{0..5}.txt`
+    /// and `root/top.rs`.
+    fn create_sample_tree(root: &std::path::Path) {
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        File::create(root.join("a/b/file2.rs")).unwrap();
+        File::create(root.join("a/file1.rs")).unwrap();
+        fs::create_dir_all(root.join("c")).unwrap();
+        for i in 0..5 {
+            File::create(root.join(format!("c/{}.txt", i))).unwrap();
+        }
+        File::create(root.join("top.rs")).unwrap();
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        create_sample_tree(&root);
+
+        let options = TreeOptions {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+
+        let expected = format!("{}\n├── a\n├── c\n└── top.rs\n", root_name);
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_dirs_only() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        create_sample_tree(&root);
+
+        let options = TreeOptions {
+            dirs_only: true,
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+
+        let expected = format!("{}\n├── a\n│   └── b\n└── c\n", root_name);
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_collapse_large_dirs() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        create_sample_tree(&root);
+
+        let options = TreeOptions {
+            collapse: Some(1),
+            ..Default::default()
+        };
+        let tree = collect_tree(root.clone(), &[], &WalkOptions::default(), &options).unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+
+        let expected = format!(
+            "{root}\n\
+├── a\n\
+│   └── … (2 files, 1 dir)\n\
+├── c\n\
+│   └── … (5 files)\n\
+└── top.rs\n",
+            root = root_name
+        );
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn test_empty_and_skipped_dirs() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+
+        fs::create_dir(root.join(".git")).unwrap();
+        let mut gitignore = File::create(root.join(".gitignore")).unwrap();
+        writeln!(gitignore, "target/").unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        File::create(root.join("target/debug/app")).unwrap();
+        fs::create_dir(root.join("empty")).unwrap();
+        File::create(root.join("main.rs")).unwrap();
+        File::create(root.join("notes.log")).unwrap();
+
+        let options = TreeOptions {
+            empty_dirs: true,
+            show_skipped: true,
+            ..Default::default()
+        };
+        let tree = collect_tree(
+            root.clone(),
+            &["*.log".into()],
+            &WalkOptions::default(),
+            &options,
+        )
+        .unwrap();
+        let root_name = root.file_name().unwrap().to_str().unwrap();
+
+        let expected = format!(
+            "{}\n\
+├── .gitignore\n\
+├── empty\n\
+├── main.rs\n\
+├── notes.log [excluded]\n\
+└── target/ [ignored]\n",
+            root_name
+        );
+        assert_eq!(tree, expected);
+    }
+
+    /// Build an in-memory tree of `files` files spread over `dirs` wide
+    /// directories, as `collect_tree` would.
+    fn build_wide_tree(dirs: usize, files: usize) -> TreeNode {
+        let mut root = TreeNode::new("root".into());
+        for i in 0..files {
+            let path = std::path::PathBuf::from(format!("d{}/f{}.rs", i % dirs, i));
+            let comps: Vec<&std::ffi::OsStr> = path.iter().collect();
+            root.insert(&comps, Metrics::default());
+        }
+        root
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_names_stay_distinct() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut root = TreeNode::new("root".into());
+        for name in [&b"a\xfe"[..], &b"a\xff"[..]] {
+            root.insert(&[std::ffi::OsStr::from_bytes(name)], Metrics::default());
+        }
+        assert_eq!(root.children.len(), 2);
+        let keys: Vec<&[u8]> = root.children.keys().map(|k| k.as_bytes()).collect();
+        assert_eq!(keys, vec![&b"a\xfe"[..], &b"a\xff"[..]]);
+
+        let tree = super::AsciiRenderer.render(&root, &TreeOptions::default());
+        assert_eq!(tree, "root\n├── a\u{fffd}\n└── a\u{fffd}\n");
+    }
+
+    /// Benchmark: walk and render a 100k-file tree on disk.
+    ///
+    /// Run with `cargo test --release bench_tree_100k_files -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_tree_100k_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        for d in 0..100 {
+            let sub = root.join(format!("dir{:03}", d));
+            fs::create_dir(&sub).unwrap();
+            for f in 0..1000 {
+                File::create(sub.join(format!("file{:04}.rs", f))).unwrap();
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let tree =
+            collect_tree(root, &[], &WalkOptions::default(), &TreeOptions::default()).unwrap();
+        println!(
+            "collect_tree: 100k files, {} lines in {:?}",
+            tree.lines().count(),
+            start.elapsed()
+        );
+
+        let start = std::time::Instant::now();
+        let root = build_wide_tree(1, 100_000);
+        let elapsed = start.elapsed();
+        println!("insert: 100k files in one directory in {:?}", elapsed);
+        assert_eq!(root.children.len(), 1);
+        // Indexed inserts take well under a second; a linear scan over the
+        // children would take minutes.
+        assert!(elapsed < std::time::Duration::from_secs(10));
+    }
 }
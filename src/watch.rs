@@ -0,0 +1,184 @@
+use anyhow::{anyhow, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// Files that changed between two collections of the same root.
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub added: Vec<PathBuf>,
+    pub modified: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl Changes {
+    /// Classify the `touched` paths reported by the watcher against the file
+    /// lists collected `before` and `after` the change.
+    ///
+    /// Paths that are in neither list (ignored or excluded files) are dropped.
+    pub fn between(before: &[PathBuf], after: &[PathBuf], touched: &[PathBuf]) -> Self {
+        let before: HashSet<&PathBuf> = before.iter().collect();
+        let after_set: HashSet<&PathBuf> = after.iter().collect();
+        let touched: HashSet<&PathBuf> = touched.iter().collect();
+
+        let mut changes = Changes::default();
+        for path in after {
+            if !before.contains(path) {
+                changes.added.push(path.clone());
+            } else if touched.contains(path) {
+                changes.modified.push(path.clone());
+            }
+        }
+        for path in before {
+            if !after_set.contains(path) {
+                changes.removed.push((*path).clone());
+            }
+        }
+
+        changes.added.sort();
+        changes.modified.sort();
+        changes.removed.sort();
+        changes
+    }
+
+    /// Whether nothing relevant changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Render the changes as a one-line summary with paths relative to `root`.
+    pub fn describe(&self, root: &Path) -> String {
+        let mut parts = Vec::new();
+        for (label, paths) in [
+            ("modified", &self.modified),
+            ("added", &self.added),
+            ("removed", &self.removed),
+        ] {
+            if paths.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = paths
+                .iter()
+                .map(|p| p.strip_prefix(root).unwrap_or(p).display().to_string())
+                .collect();
+            parts.push(format!("{} {}: {}", paths.len(), label, names.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+/// Watch `root` recursively and call `on_change` with the touched paths each
+/// time a burst of file system events has been quiet for `debounce`.
+///
+/// Access events (including our own reads while regenerating) and events
+/// inside `.git` are dropped. Errors returned by `on_change` are printed and
+/// watching continues; this only returns if the watcher fails.
+pub fn watch<F>(root: &Path, debounce: Duration, mut on_change: F) -> Result<()>
+where
+    F: FnMut(&[PathBuf]) -> Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| anyhow!("failed to start watcher: {}", e))?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .map_err(|e| anyhow!("failed to watch {}: {}", root.display(), e))?;
+
+    let mut touched: Vec<PathBuf> = Vec::new();
+    loop {
+        let result = if touched.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(debounce)
+        };
+
+        match result {
+            Ok(event) => {
+                let event = event.map_err(|e| anyhow!("watch error: {}", e))?;
+                touched.extend(relevant_paths(event));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                touched.sort();
+                touched.dedup();
+                if let Err(err) = on_change(&touched) {
+                    eprintln!("{}", err);
+                }
+                touched.clear();
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// The paths of `event` worth reacting to: content, metadata or name changes
+/// outside `.git`.
+fn relevant_paths(event: Event) -> Vec<PathBuf> {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .into_iter()
+        .filter(|path| !in_git_dir(path))
+        .collect()
+}
+
+/// Whether `path` lies inside a `.git` directory.
+fn in_git_dir(path: &Path) -> bool {
+    path.components()
+        .any(|c| matches!(c, Component::Normal(name) if name == ".git"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_changes_between() {
+        let before = paths(&["/r/a.rs", "/r/b.rs", "/r/c.rs"]);
+        let after = paths(&["/r/a.rs", "/r/c.rs", "/r/d.rs"]);
+        let touched = paths(&["/r/c.rs", "/r/d.rs", "/r/b.rs", "/r/target/out"]);
+
+        let changes = Changes::between(&before, &after, &touched);
+        assert_eq!(changes.added, paths(&["/r/d.rs"]));
+        assert_eq!(changes.modified, paths(&["/r/c.rs"]));
+        assert_eq!(changes.removed, paths(&["/r/b.rs"]));
+        assert_eq!(
+            changes.describe(Path::new("/r")),
+            "1 modified: c.rs; 1 added: d.rs; 1 removed: b.rs"
+        );
+    }
+
+    #[test]
+    fn test_changes_ignores_untracked_paths() {
+        let files = paths(&["/r/a.rs"]);
+        let touched = paths(&["/r/target/debug/app"]);
+
+        assert!(Changes::between(&files, &files, &touched).is_empty());
+    }
+
+    #[test]
+    fn test_relevant_paths_skip_access_and_git() {
+        use notify::event::{AccessKind, CreateKind};
+
+        let access = Event::new(EventKind::Access(AccessKind::Any)).add_path("/r/a.rs".into());
+        assert!(relevant_paths(access).is_empty());
+
+        let create = Event::new(EventKind::Create(CreateKind::File))
+            .add_path("/r/a.rs".into())
+            .add_path("/r/.git/index".into());
+        assert_eq!(relevant_paths(create), paths(&["/r/a.rs"]));
+    }
+
+    #[test]
+    fn test_in_git_dir() {
+        assert!(in_git_dir(Path::new("/r/.git/index")));
+        assert!(!in_git_dir(Path::new("/r/src/git.rs")));
+    }
+}